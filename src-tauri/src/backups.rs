@@ -0,0 +1,186 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{info, warn};
+
+use crate::config::{self, ConfigChange, OpenClawConfig};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::json5;
+use crate::settings;
+
+/// A saved copy of openclaw.json, identified by its file name.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigBackup {
+    pub id: String,
+    pub path: PathBuf,
+    /// RFC 3339 time the backup was taken.
+    pub created: String,
+    pub size: u64,
+}
+
+/// What restoring a backup would change in the current config.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupPreview {
+    pub backup: ConfigBackup,
+    pub changes: Vec<ConfigChange>,
+    /// The restore also changes settings the gateway reads at startup.
+    pub gateway_changed: bool,
+}
+
+/// `~/.openclaw/openclaw.json` -> `~/.openclaw/backups/`
+pub fn backup_dir(config_path: &Path) -> PathBuf {
+    config_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join("backups")
+}
+
+/// Copy the current config into the backup dir before it is overwritten,
+/// unless it is identical to the newest backup. Old backups beyond the
+/// configured count are removed.
+pub fn create(config_path: &Path) -> io::Result<Option<PathBuf>> {
+    let Ok(current) = fs::read(config_path) else {
+        return Ok(None);
+    };
+    let dir = backup_dir(config_path);
+    fs::create_dir_all(&dir)?;
+
+    let existing = backup_files(&dir);
+    if let Some(newest) = existing.first() {
+        if fs::read(newest).is_ok_and(|b| b == current) {
+            return Ok(None);
+        }
+    }
+
+    // Millisecond timestamps sort chronologically and rarely collide;
+    // ':' is avoided so the names stay friendly to other tools.
+    let stamp = humantime::format_rfc3339_millis(SystemTime::now())
+        .to_string()
+        .replace(':', "-");
+    let stem = config_path.file_stem().unwrap_or_default().to_string_lossy();
+    let path = dir.join(format!("{}-{}.json", stem, stamp));
+    fs::write(&path, &current)?;
+    // Backups hold API keys just like the config itself
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+    info!(path = %path.display(), "config backed up");
+
+    let keep = settings::load_settings().backups.keep;
+    for old in backup_files(&dir).into_iter().skip(keep.max(1)) {
+        if let Err(e) = fs::remove_file(&old) {
+            warn!(path = %old.display(), error = %e, "failed to remove old config backup");
+        }
+    }
+    Ok(Some(path))
+}
+
+/// In every backup of `config_path`, put `new` in place of `old` at the
+/// config path given by `segments`, for each `(segments, old, new)`, e.g.
+/// to take secrets moved out of the config out of its copies too. Returns
+/// how many backups were rewritten.
+pub fn replace_values(config_path: &Path, replacements: &[(Vec<String>, String, String)]) -> usize {
+    let mut rewritten = 0;
+    for path in backup_files(&backup_dir(config_path)) {
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(mut doc) = json5::parse(&text) else {
+            continue;
+        };
+
+        let mut changed = false;
+        for (segments, old, new) in replacements {
+            let slot = segments
+                .iter()
+                .try_fold(&mut doc, |value, segment| value.get_mut(segment.as_str()));
+            if let Some(slot) = slot.filter(|v| v.as_str() == Some(old.as_str())) {
+                *slot = Value::String(new.clone());
+                changed = true;
+            }
+        }
+        if !changed {
+            continue;
+        }
+
+        let Some(content) =
+            json5::edit(&text, &doc).or_else(|| serde_json::to_string_pretty(&doc).ok())
+        else {
+            continue;
+        };
+        match config::write_atomic(&path, content.as_bytes()) {
+            Ok(()) => rewritten += 1,
+            Err(e) => warn!(path = %path.display(), error = %e, "failed to rewrite config backup"),
+        }
+    }
+    rewritten
+}
+
+/// Backup files in `dir`, newest first.
+fn backup_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    // Names embed the timestamp, so reverse name order is newest first
+    files.sort();
+    files.reverse();
+    files
+}
+
+pub fn list(config_path: &Path) -> Vec<ConfigBackup> {
+    backup_files(&backup_dir(config_path))
+        .into_iter()
+        .filter_map(|path| describe(&path))
+        .collect()
+}
+
+fn describe(path: &Path) -> Option<ConfigBackup> {
+    let meta = fs::metadata(path).ok()?;
+    Some(ConfigBackup {
+        id: path.file_name()?.to_string_lossy().into_owned(),
+        path: path.to_path_buf(),
+        created: humantime::format_rfc3339_seconds(meta.modified().ok()?).to_string(),
+        size: meta.len(),
+    })
+}
+
+/// Resolve a backup id to its file, refusing anything outside the backup dir.
+fn find(config_path: &Path, id: &str) -> AppResult<PathBuf> {
+    let path = backup_dir(config_path).join(id);
+    if id.contains('/') || id.starts_with('.') || !path.is_file() {
+        return Err(
+            AppError::new(ErrorKind::InvalidInput, format!("No config backup named \"{}\"", id))
+                .with_path(&path),
+        );
+    }
+    Ok(path)
+}
+
+pub fn preview(config_path: &Path, id: &str) -> AppResult<BackupPreview> {
+    let path = find(config_path, id)?;
+    let backup = config::read_document(&path)?;
+    let current = config::read_document(config_path)?;
+
+    let changes = config::diff(&current, &backup);
+    Ok(BackupPreview {
+        backup: describe(&path).ok_or_else(|| AppError::internal("Backup disappeared"))?,
+        gateway_changed: changes.iter().any(ConfigChange::affects_gateway),
+        changes,
+    })
+}
+
+/// Replace the config with a backup. The config being replaced is itself
+/// backed up first, so a restore can be undone.
+pub fn restore(config_path: &Path, id: &str) -> AppResult<OpenClawConfig> {
+    let path = find(config_path, id)?;
+    let backup = config::read_document(&path)?;
+    info!(backup = %path.display(), "restoring config backup");
+    config::replace_document(config_path, &backup)
+}
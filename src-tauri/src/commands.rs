@@ -1,6 +1,22 @@
+use crate::backups::{self, BackupPreview, ConfigBackup};
 use crate::config;
+use crate::doctor::{self, DoctorReport};
+use crate::connection::{self, Connection};
+use crate::error::{AppError, AppResult, ErrorKind};
 use crate::gateway;
+use crate::gateway_env;
+use crate::instance::{self, DEFAULT_INSTANCE};
+use crate::logging;
+use crate::logs::{self, LogLevel, LogLine};
+use crate::profiles::{self, ProfileSummary};
+use crate::proxy;
+use crate::proxy_check;
+use crate::secrets;
+use crate::settings;
+use crate::tray;
 use serde::Serialize;
+use std::path::PathBuf;
+use tauri::AppHandle;
 
 #[derive(Debug, Clone, Serialize)]
 pub struct GatewayInfo {
@@ -8,35 +24,530 @@ pub struct GatewayInfo {
     pub token: String,
     pub port: u16,
     pub full_url: String,
+    /// "local" or "remote".
+    pub mode: String,
+    pub bind: String,
+    /// Reachable from other devices; `None` when bound to loopback.
+    pub lan_url: Option<String>,
+    /// `lan_url` including the token, to be rendered as a QR code.
+    pub qr_payload: Option<String>,
+    pub warnings: Vec<String>,
+    /// Where the config path and port come from; `None` for remote gateways.
+    pub sources: Option<config::ConfigSources>,
+}
+
+fn gateway_info(conn: &Connection) -> GatewayInfo {
+    let (mode, bind, lan_url, qr_payload, sources) = match conn {
+        Connection::Local(gw) => (
+            "local",
+            gw.bind.clone(),
+            gw.lan_url(),
+            gw.lan_full_url(),
+            config::config_sources(),
+        ),
+        Connection::Remote { .. } => ("remote", String::new(), None, None, None),
+    };
+
+    GatewayInfo {
+        url: conn.base_url(),
+        token: conn.token().to_string(),
+        port: conn.port(),
+        full_url: conn.full_url(),
+        mode: mode.to_string(),
+        bind,
+        lan_url,
+        qr_payload,
+        warnings: conn.warnings(),
+        sources,
+    }
+}
+
+/// A local gateway instance as shown in the instances list.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceStatus {
+    pub id: String,
+    pub state_dir: PathBuf,
+    pub config_path: PathBuf,
+    pub log_path: PathBuf,
+    pub autostart: bool,
+    /// `None` when the instance's config can't be read.
+    pub port: Option<u16>,
+    pub full_url: Option<String>,
+    pub state: gateway::GatewayState,
+    pub healthy: bool,
+}
+
+fn local_gateway_info(mut gw: config::GatewayConfig) -> GatewayInfo {
+    // Configs fresh from a write don't have the overrides applied yet
+    if let Some((port, _)) = config::port_override() {
+        gw.port = port;
+    }
+    gateway_info(&Connection::Local(gw))
 }
 
 #[tauri::command]
-pub fn get_gateway_info() -> Result<GatewayInfo, String> {
-    let cfg = config::load_config()?;
-    let gw = &cfg.gateway;
+pub fn get_gateway_info() -> AppResult<GatewayInfo> {
+    Ok(gateway_info(&connection::current()?))
+}
+
+#[tauri::command]
+pub fn read_config() -> AppResult<config::OpenClawConfig> {
+    config::load_config()
+}
+
+/// Result of `update_config`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigUpdate {
+    pub config: config::OpenClawConfig,
+    /// Some change needs a gateway restart to take effect.
+    pub gateway_changed: bool,
+    /// The gateway was restarted to apply it. When the gateway changed but
+    /// wasn't restarted (e.g. it is attached), the UI offers a restart.
+    pub restarted: bool,
+}
+
+/// Apply a JSON merge patch to openclaw.json, preserving unknown keys and
+/// order, then restart the managed gateway if the patch touched settings it
+/// only reads at startup. The config watcher skips our own writes, so it
+/// won't do that for us.
+#[tauri::command]
+pub async fn update_config(patch: serde_json::Value) -> AppResult<ConfigUpdate> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = config::config_path().ok_or_else(AppError::home_dir_unknown)?;
+        let old = if path.exists() {
+            config::read_document(&path)?
+        } else {
+            serde_json::json!({})
+        };
 
-    Ok(GatewayInfo {
-        url: gw.base_url(),
-        token: gw.auth.token.clone(),
-        port: gw.port,
-        full_url: gw.full_url(),
+        let cfg = config::update_config(&patch)?;
+        let new = config::read_document(&path)?;
+        let gateway_changed = config::diff(&old, &new)
+            .iter()
+            .any(config::ConfigChange::affects_gateway);
+        let restarted = gateway_changed && gateway::restart_if_managed(DEFAULT_INSTANCE);
+
+        Ok(ConfigUpdate {
+            config: cfg,
+            gateway_changed,
+            restarted,
+        })
     })
+    .await
+    .map_err(AppError::task_join)?
 }
 
 #[tauri::command]
-pub fn check_gateway_status() -> Result<bool, String> {
-    let cfg = config::load_config()?;
-    Ok(gateway::check_health(&cfg.gateway.base_url()))
+pub fn get_gateway_config() -> AppResult<config::GatewayConfig> {
+    Ok(config::load_config()?.gateway)
 }
 
+/// Change port, bind or auth from the settings screen, then restart the
+/// managed gateway so the new settings take effect.
 #[tauri::command]
-pub fn get_gateway_url() -> Result<String, String> {
-    let cfg = config::load_config()?;
-    Ok(cfg.gateway.full_url())
+pub async fn update_gateway_settings(update: config::GatewayUpdate) -> AppResult<GatewayInfo> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let current = config::load_config()?;
+        if let Some(port) = update.port.filter(|p| *p != current.gateway.port) {
+            config::ensure_port_not_overridden()?;
+            if let Some(conflict) = gateway::check_port_conflict(port) {
+                return Err(AppError::new(ErrorKind::PortUnavailable, conflict.message));
+            }
+        }
+
+        let cfg = config::update_gateway(&update)?;
+        gateway::restart_if_managed(DEFAULT_INSTANCE);
+        Ok(local_gateway_info(cfg.gateway))
+    })
+    .await
+    .map_err(AppError::task_join)?
 }
 
 #[tauri::command]
-pub fn start_gateway() -> Result<(), String> {
+pub fn check_gateway_status() -> AppResult<bool> {
+    Ok(gateway::check_health(&connection::current()?.base_url()))
+}
+
+#[tauri::command]
+pub fn get_gateway_url() -> AppResult<String> {
+    Ok(connection::current()?.full_url())
+}
+
+#[tauri::command]
+pub fn start_gateway() -> AppResult<()> {
     gateway::ensure_started();
     Ok(())
 }
+
+#[tauri::command]
+pub fn get_gateway_state() -> gateway::GatewayState {
+    gateway::current_state()
+}
+
+#[tauri::command]
+pub fn check_port_conflict() -> AppResult<Option<gateway::PortConflict>> {
+    use gateway::GatewayState;
+
+    // While our own gateway is coming up, it is the one holding the port.
+    if matches!(
+        gateway::current_state(),
+        GatewayState::Starting
+            | GatewayState::Running { .. }
+            | GatewayState::Attached { .. }
+            | GatewayState::Backoff { .. }
+            | GatewayState::Remote { .. }
+    ) {
+        return Ok(None);
+    }
+
+    let cfg = config::load_config()?;
+    Ok(gateway::check_port_conflict(cfg.gateway.port))
+}
+
+/// Move the gateway to `port` (or the nearest free one), rewrite the config
+/// and start the gateway again.
+#[tauri::command]
+pub async fn resolve_port_conflict(port: Option<u16>) -> AppResult<GatewayInfo> {
+    tauri::async_runtime::spawn_blocking(move || {
+        config::ensure_port_not_overridden()?;
+        let cfg = config::load_config()?;
+        let port = match port {
+            Some(p) => p,
+            None => gateway::find_free_port(cfg.gateway.port)
+                .ok_or_else(|| {
+                    AppError::new(
+                        ErrorKind::PortUnavailable,
+                        format!("No free port found near {}", cfg.gateway.port),
+                    )
+                })?,
+        };
+
+        if let Some(conflict) = gateway::check_port_conflict(port) {
+            return Err(AppError::new(ErrorKind::PortUnavailable, conflict.message));
+        }
+
+        config::set_gateway_port(port)?;
+        gateway::ensure_started();
+
+        let cfg = config::load_config()?;
+        Ok(local_gateway_info(cfg.gateway))
+    })
+    .await
+    .map_err(AppError::task_join)?
+}
+
+#[tauri::command]
+pub async fn list_gateway_instances() -> AppResult<Vec<InstanceStatus>> {
+    tauri::async_runtime::spawn_blocking(|| {
+        instance::all()
+            .into_iter()
+            .map(|inst| {
+                let cfg = if inst.is_default() {
+                    config::load_config()
+                } else {
+                    config::load_config_from(&inst.config_path)
+                };
+                let gw = cfg.ok().map(|c| c.gateway);
+                let healthy = gw.as_ref().is_some_and(|gw| gateway::check_health(&gw.base_url()));
+                InstanceStatus {
+                    state: gateway::state_of(&inst.id),
+                    port: gw.as_ref().map(|gw| gw.port),
+                    full_url: gw.as_ref().map(|gw| gw.full_url()),
+                    healthy,
+                    id: inst.id,
+                    state_dir: inst.state_dir,
+                    config_path: inst.config_path,
+                    log_path: inst.log_path,
+                    autostart: inst.autostart,
+                }
+            })
+            .collect()
+    })
+    .await
+    .map_err(AppError::task_join)
+}
+
+/// Create a gateway instance with its own state dir, on `port` or the
+/// first free port after the default gateway's that no other instance is
+/// configured to use.
+#[tauri::command]
+pub fn add_gateway_instance(
+    name: String,
+    state_dir: Option<PathBuf>,
+    port: Option<u16>,
+) -> AppResult<instance::Instance> {
+    let port = match port {
+        Some(p) => p,
+        None => {
+            let base = config::load_config().map(|c| c.gateway.port).unwrap_or(config::DEFAULT_PORT);
+            gateway::find_free_port_excluding(base, &instance::assigned_ports()).ok_or_else(|| {
+                AppError::new(
+                    ErrorKind::PortUnavailable,
+                    format!("No free port found near {}", base),
+                )
+            })?
+        }
+    };
+    instance::add(&name, state_dir, port)
+}
+
+#[tauri::command]
+pub async fn remove_gateway_instance(id: String) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || {
+        instance::remove(&id)?;
+        gateway::stop(&id);
+        Ok(())
+    })
+    .await
+    .map_err(AppError::task_join)?
+}
+
+#[tauri::command]
+pub async fn start_gateway_instance(id: String) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || gateway::start_instance(&id))
+        .await
+        .map_err(AppError::task_join)?
+}
+
+#[tauri::command]
+pub async fn stop_gateway_instance(id: String) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || gateway::stop(&id))
+        .await
+        .map_err(AppError::task_join)
+}
+
+/// Log of `instance`, or of the default gateway when not given.
+fn instance_log_path(instance: Option<&str>) -> AppResult<PathBuf> {
+    Ok(instance::get(instance.unwrap_or(DEFAULT_INSTANCE))?.log_path)
+}
+
+#[tauri::command]
+pub fn tail_gateway_log(
+    lines: Option<usize>,
+    level: Option<LogLevel>,
+    instance: Option<String>,
+) -> AppResult<Vec<LogLine>> {
+    let path = instance_log_path(instance.as_deref())?;
+    logs::tail(&path, lines.unwrap_or(500), level)
+}
+
+#[tauri::command]
+pub async fn search_gateway_log(
+    query: String,
+    level: Option<LogLevel>,
+    limit: Option<usize>,
+    instance: Option<String>,
+) -> AppResult<Vec<LogLine>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let path = instance_log_path(instance.as_deref())?;
+        logs::search(&path, &query, level, limit.unwrap_or(1000))
+    })
+    .await
+    .map_err(AppError::task_join)?
+}
+
+#[tauri::command]
+pub fn open_log_window(app: AppHandle) -> AppResult<()> {
+    logs::show_window(&app)
+}
+
+#[tauri::command]
+pub fn get_log_level() -> String {
+    logging::current_level()
+}
+
+#[tauri::command]
+pub fn set_log_level(level: String) -> AppResult<()> {
+    logging::set_level(&level)
+}
+
+#[tauri::command]
+pub fn get_connection_settings() -> settings::ConnectionSettings {
+    settings::load_settings().active_connection()
+}
+
+/// Switch between the local gateway and a remote one, then connect.
+#[tauri::command]
+pub async fn set_connection(connection: settings::ConnectionSettings) -> AppResult<GatewayInfo> {
+    tauri::async_runtime::spawn_blocking(move || {
+        connection::validate(&connection)?;
+
+        let mut s = settings::load_settings();
+        match s.active_profile_mut() {
+            Some(profile) => profile.connection = connection,
+            None => s.connection = connection,
+        }
+        settings::save_settings(&s)?;
+
+        gateway::ensure_started();
+        Ok(gateway_info(&connection::current()?))
+    })
+    .await
+    .map_err(AppError::task_join)?
+}
+
+#[tauri::command]
+pub fn list_profiles() -> Vec<ProfileSummary> {
+    profiles::list()
+}
+
+/// Add or replace a named profile. Window state is kept from the existing one.
+#[tauri::command]
+pub fn save_profile(app: AppHandle, profile: settings::Profile) -> AppResult<Vec<ProfileSummary>> {
+    profiles::save(profile)?;
+    tray::refresh_profiles(&app);
+    Ok(profiles::list())
+}
+
+#[tauri::command]
+pub fn delete_profile(app: AppHandle, name: String) -> AppResult<Vec<ProfileSummary>> {
+    profiles::delete(&name)?;
+    tray::refresh_profiles(&app);
+    Ok(profiles::list())
+}
+
+/// Activate a profile (`None` for the default connection) and reconnect.
+#[tauri::command]
+pub async fn switch_profile(app: AppHandle, name: Option<String>) -> AppResult<()> {
+    tauri::async_runtime::spawn_blocking(move || profiles::switch(&app, name.as_deref()))
+        .await
+        .map_err(AppError::task_join)?
+}
+
+/// Config file of `instance`, or of the default gateway when not given.
+fn instance_config_path(instance: Option<&str>) -> AppResult<PathBuf> {
+    Ok(instance::get(instance.unwrap_or(DEFAULT_INSTANCE))?.config_path)
+}
+
+/// Backups of openclaw.json, newest first.
+#[tauri::command]
+pub fn list_config_backups(instance: Option<String>) -> AppResult<Vec<ConfigBackup>> {
+    Ok(backups::list(&instance_config_path(instance.as_deref())?))
+}
+
+/// What restoring backup `id` would change, without changing anything.
+#[tauri::command]
+pub fn preview_config_backup(id: String, instance: Option<String>) -> AppResult<BackupPreview> {
+    backups::preview(&instance_config_path(instance.as_deref())?, &id)
+}
+
+/// Restore backup `id` and restart the gateway if its startup settings change.
+#[tauri::command]
+pub async fn restore_config_backup(
+    id: String,
+    instance: Option<String>,
+) -> AppResult<config::OpenClawConfig> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let instance = instance.as_deref().unwrap_or(DEFAULT_INSTANCE);
+        let path = instance_config_path(Some(instance))?;
+        let preview = backups::preview(&path, &id)?;
+        let cfg = backups::restore(&path, &id)?;
+        if preview.gateway_changed {
+            gateway::restart_if_managed(instance);
+        }
+        Ok(cfg)
+    })
+    .await
+    .map_err(AppError::task_join)?
+}
+
+/// Validate openclaw.json and list every problem with its position and fix.
+#[tauri::command]
+pub fn check_config(instance: Option<String>) -> AppResult<DoctorReport> {
+    doctor::check(&instance_config_path(instance.as_deref())?)
+}
+
+/// Apply the suggested fixes of the diagnostics in `ids`, restarting the
+/// gateway if that changed its startup settings.
+#[tauri::command]
+pub async fn apply_config_fixes(
+    ids: Vec<String>,
+    instance: Option<String>,
+) -> AppResult<DoctorReport> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let instance = instance.as_deref().unwrap_or(DEFAULT_INSTANCE);
+        let path = instance_config_path(Some(instance))?;
+        let (report, gateway_changed) = doctor::apply_fixes(&path, &ids)?;
+        if gateway_changed && report.ok {
+            gateway::restart_if_managed(instance);
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(AppError::task_join)?
+}
+
+/// Where API keys and channel tokens are stored on this machine.
+#[tauri::command]
+pub async fn get_secret_backend() -> AppResult<secrets::Backend> {
+    // The first call probes the keyring over D-Bus
+    tauri::async_runtime::spawn_blocking(secrets::backend)
+        .await
+        .map_err(AppError::task_join)
+}
+
+/// Move plaintext API keys, channel tokens and the proxy password from
+/// openclaw.json into secret storage. Returns the config paths that were moved.
+#[tauri::command]
+pub async fn secure_config_secrets(instance: Option<String>) -> AppResult<Vec<String>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let instance = instance.as_deref().unwrap_or(DEFAULT_INSTANCE);
+        let moved = secrets::secure_config(&instance_config_path(Some(instance))?)?;
+        // A running gateway knows the values but not the new references
+        if !moved.is_empty() {
+            gateway::restart_if_managed(instance);
+        }
+        Ok(moved)
+    })
+    .await
+    .map_err(AppError::task_join)?
+}
+
+/// Which proxy the system configuration picks for `url`, evaluating the
+/// PAC script when one is configured.
+#[tauri::command]
+pub async fn proxy_for_url(url: String) -> AppResult<proxy::ProxyChoice> {
+    tauri::async_runtime::spawn_blocking(move || proxy::choose(&url))
+        .await
+        .map_err(AppError::task_join)?
+}
+
+/// Check that `url` is a working proxy by tunnelling to `target`
+/// (api.telegram.org when `None`) through it. Without a `url` the system
+/// proxy is tested, credentials included, which the UI only sees redacted.
+#[tauri::command]
+pub async fn test_proxy(
+    url: Option<String>,
+    target: Option<String>,
+) -> AppResult<proxy_check::ProxyTestResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let url = match url {
+            Some(url) => url,
+            None => {
+                let system = proxy::resolve();
+                system
+                    .https_proxy()
+                    .or(system.socks.as_deref())
+                    .map(str::to_string)
+                    .ok_or_else(|| {
+                        AppError::new(ErrorKind::InvalidInput, "No system proxy is configured")
+                    })?
+            }
+        };
+        proxy_check::test_proxy(&url, target.as_deref())
+    })
+    .await
+    .map_err(AppError::task_join)?
+}
+
+/// The environment the gateway instance is (or would be) started with,
+/// with secrets and credentials redacted.
+#[tauri::command]
+pub async fn get_gateway_env(instance: Option<String>) -> AppResult<Vec<gateway_env::EnvVar>> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let inst = instance::get(instance.as_deref().unwrap_or(DEFAULT_INSTANCE))?;
+        Ok(gateway_env::build(&inst).redacted())
+    })
+    .await
+    .map_err(AppError::task_join)?
+}
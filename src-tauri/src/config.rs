@@ -1,63 +1,840 @@
-use serde::Deserialize;
-use std::fs;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
 
-#[derive(Debug, Clone, Deserialize)]
+use crate::backups;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::json5;
+
+/// Typed view of openclaw.json. Every section keeps the keys it doesn't
+/// model in `extra`, so a read/serialize round trip loses nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct OpenClawConfig {
     pub gateway: GatewayConfig,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub providers: BTreeMap<String, ProviderConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_model: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub channels: BTreeMap<String, ChannelConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyConfig>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GatewayConfig {
     pub port: u16,
     #[serde(default = "default_bind")]
     pub bind: String,
     #[serde(default)]
     pub auth: AuthConfig,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AuthConfig {
     #[serde(default)]
     pub mode: String,
     #[serde(default)]
     pub token: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ChannelConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ProxyConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Credentials for a proxy URL that has none of its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Usually a secret reference rather than the password itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Changes to the gateway section requested from the settings screen.
+/// `None` leaves a field as it is.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewayUpdate {
+    pub port: Option<u16>,
+    pub bind: Option<String>,
+    pub auth_mode: Option<String>,
+    /// Replace the auth token with a freshly generated one.
+    #[serde(default)]
+    pub rotate_token: bool,
+}
+
+/// Keys whose values never appear in diffs sent to the frontend.
+const SECRET_KEYS: [&str; 4] = ["token", "apiKey", "password", "secret"];
+
+/// Documents this app wrote last, per path, so the config watcher can tell
+/// its own writes from external edits.
+static OWN_WRITES: Mutex<BTreeMap<PathBuf, Value>> = Mutex::new(BTreeMap::new());
+
+/// One changed value between two versions of openclaw.json, addressed by a
+/// dotted path such as `gateway.auth.mode`. `None` means absent.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+    pub path: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl ConfigChange {
+    /// The gateway reads these only at startup, so changing them needs a restart.
+    pub fn affects_gateway(&self) -> bool {
+        ["gateway", "proxy"]
+            .iter()
+            .any(|s| self.path == *s || self.path.starts_with(&format!("{}.", s)))
+    }
+}
+
+/// Port `openclaw` uses when none is configured.
+pub const DEFAULT_PORT: u16 = 18789;
+
+pub const AUTH_MODES: [&str; 2] = ["token", "none"];
+
 fn default_bind() -> String {
     "loopback".to_string()
 }
 
-fn config_path() -> Option<PathBuf> {
-    dirs::home_dir().map(|h| h.join(".openclaw").join("openclaw.json"))
+/// Where an effective setting came from. Later variants take precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueSource {
+    Default,
+    File,
+    Env,
+    Cli,
+}
+
+/// Settings given as command-line flags, which override both openclaw.json
+/// and the environment.
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    pub config: Option<PathBuf>,
+    pub port: Option<u16>,
+}
+
+/// Environment variable naming the openclaw.json to use.
+pub const CONFIG_ENV: &str = "OPENCLAW_CONFIG";
+/// Environment variable overriding `gateway.port`.
+pub const PORT_ENV: &str = "OPENCLAW_GATEWAY_PORT";
+
+static CLI_OVERRIDES: OnceLock<CliOverrides> = OnceLock::new();
+
+/// Record the flags parsed in `main`. Only the first call has an effect.
+pub fn set_cli_overrides(overrides: CliOverrides) {
+    let _ = CLI_OVERRIDES.set(overrides);
+}
+
+fn cli_overrides() -> &'static CliOverrides {
+    CLI_OVERRIDES.get_or_init(CliOverrides::default)
+}
+
+/// Effective sources of the settings that can be overridden, as reported
+/// by `get_gateway_info`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSources {
+    pub config_path: PathBuf,
+    pub config_path_source: ValueSource,
+    pub port: ValueSource,
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    resolve_config_path().map(|(path, _)| path)
+}
+
+/// openclaw.json of the default instance: `--config`, then
+/// `$OPENCLAW_CONFIG`, then `~/.openclaw/openclaw.json`.
+pub fn resolve_config_path() -> Option<(PathBuf, ValueSource)> {
+    if let Some(path) = &cli_overrides().config {
+        return Some((path.clone(), ValueSource::Cli));
+    }
+    if let Some(path) = std::env::var_os(CONFIG_ENV).filter(|p| !p.is_empty()) {
+        return Some((PathBuf::from(path), ValueSource::Env));
+    }
+    dirs::home_dir().map(|h| (h.join(".openclaw").join("openclaw.json"), ValueSource::Default))
+}
+
+/// Gateway port given by `--port` or `$OPENCLAW_GATEWAY_PORT`, if any.
+/// An unparsable variable is ignored rather than failing every command.
+pub fn port_override() -> Option<(u16, ValueSource)> {
+    if let Some(port) = cli_overrides().port {
+        return Some((port, ValueSource::Cli));
+    }
+    let raw = std::env::var(PORT_ENV).ok().filter(|v| !v.trim().is_empty())?;
+    match raw.trim().parse::<u16>() {
+        Ok(port) if port > 0 => Some((port, ValueSource::Env)),
+        _ => {
+            tracing::warn!(value = %raw, "ignoring invalid {}", PORT_ENV);
+            None
+        }
+    }
+}
+
+/// Refuse to change the port while `port_override` pins it, since the
+/// value written to openclaw.json wouldn't be used.
+pub fn ensure_port_not_overridden() -> AppResult<()> {
+    let Some((port, source)) = port_override() else {
+        return Ok(());
+    };
+    let origin = match source {
+        ValueSource::Cli => "the --port flag".to_string(),
+        _ => format!("${}", PORT_ENV),
+    };
+    Err(AppError::new(
+        ErrorKind::InvalidInput,
+        format!(
+            "The gateway port is set to {} by {}; change it there instead",
+            port, origin
+        ),
+    ))
 }
 
-pub fn load_config() -> Result<OpenClawConfig, String> {
-    let path = config_path().ok_or("Could not determine home directory")?;
+pub fn config_sources() -> Option<ConfigSources> {
+    let (config_path, config_path_source) = resolve_config_path()?;
+    let port = match port_override() {
+        Some((_, source)) => source,
+        None => ValueSource::File,
+    };
+    Some(ConfigSources {
+        config_path,
+        config_path_source,
+        port,
+    })
+}
+
+/// The default instance's config with the environment and command-line
+/// overrides applied. Writes always go to the file, so an overridden value
+/// stays overridden after it is changed from the app.
+#[tracing::instrument(level = "debug")]
+pub fn load_config() -> AppResult<OpenClawConfig> {
+    let path = config_path().ok_or_else(AppError::home_dir_unknown)?;
+    let mut config = load_config_from(&path)?;
+    if let Some((port, _)) = port_override() {
+        config.gateway.port = port;
+    }
+    Ok(config)
+}
+
+/// Load the config of a gateway instance other than the default one.
+pub fn load_config_from(path: &Path) -> AppResult<OpenClawConfig> {
+    let doc = read_document(path)?;
 
+    let config: OpenClawConfig =
+        serde_json::from_value(doc).map_err(|e| AppError::config_invalid(path, &e))?;
+
+    Ok(config)
+}
+
+fn read_config_file(path: &Path) -> AppResult<String> {
     if !path.exists() {
-        return Err(format!("Config not found: {}", path.display()));
+        return Err(AppError::new(
+            ErrorKind::ConfigMissing,
+            format!("Config not found: {}", path.display()),
+        )
+        .with_path(path));
     }
 
-    let content = fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    fs::read_to_string(path).map_err(|e| {
+        AppError::new(ErrorKind::ConfigRead, format!("Failed to read {}", path.display()))
+            .with_path(path)
+            .with_source(e)
+    })
+}
 
-    let config: OpenClawConfig = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse config: {}", e))?;
+/// Apply an RFC 7396 JSON merge patch to openclaw.json in place.
+///
+/// The file is edited as a document rather than re-serialized from
+/// `OpenClawConfig`, so unknown keys survive and existing keys keep their
+/// position; new keys are appended. Only changed values are rewritten, so
+/// comments elsewhere in the file are kept. A missing file is treated as `{}`.
+/// The patched document must still parse as an `OpenClawConfig`.
+#[tracing::instrument(level = "debug", skip(patch), err)]
+pub fn update_config(patch: &Value) -> AppResult<OpenClawConfig> {
+    let path = config_path().ok_or_else(AppError::home_dir_unknown)?;
+    update_config_at(&path, patch)
+}
+
+/// `update_config` for the openclaw.json at `path`.
+pub fn update_config_at(path: &Path, patch: &Value) -> AppResult<OpenClawConfig> {
+    let mut doc = if path.exists() {
+        read_document(path)?
+    } else {
+        Value::Object(Map::new())
+    };
+
+    merge_patch(&mut doc, patch);
+    replace_document(path, &doc)
+}
+
+/// The config at `path` as an untyped document. Like the OpenClaw CLI, this
+/// accepts JSON5: comments, trailing commas, unquoted keys and so on.
+pub fn read_document(path: &Path) -> AppResult<Value> {
+    let content = read_config_file(path)?;
+    json5::parse(&content).map_err(|e| AppError::config_syntax(path, &e))
+}
+
+/// Validate `doc` as an `OpenClawConfig` and write it to `path` as is.
+pub fn replace_document(path: &Path, doc: &Value) -> AppResult<OpenClawConfig> {
+    let config: OpenClawConfig = serde_json::from_value(doc.clone()).map_err(|e| {
+        AppError::new(ErrorKind::ConfigInvalid, "Update would make the config invalid")
+            .with_path(path)
+            .with_source(e)
+    })?;
 
+    write_document(path, doc)?;
     Ok(config)
 }
 
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(target) = target else {
+        unreachable!();
+    };
+
+    for (key, value) in patch {
+        if value.is_null() {
+            // shift_remove keeps the order of the remaining keys
+            target.shift_remove(key);
+        } else {
+            merge_patch(target.entry(key.as_str()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+fn write_document(path: &Path, doc: &Value) -> AppResult<()> {
+    let write_error = |e: std::io::Error| {
+        AppError::new(ErrorKind::ConfigWrite, format!("Failed to write {}", path.display()))
+            .with_path(path)
+            .with_source(e)
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(write_error)?;
+    }
+
+    // Edit the existing text where possible so comments and formatting
+    // survive; otherwise (no file yet, or it doesn't parse) write plain JSON
+    let existing = fs::read_to_string(path).ok();
+    let content = match existing.as_deref().and_then(|text| json5::edit(text, doc)) {
+        Some(content) => content,
+        None => {
+            let mut content = serde_json::to_string_pretty(doc)
+                .map_err(|e| AppError::internal("Failed to serialize config").with_source(e))?;
+            content.push('\n');
+            content
+        }
+    };
+
+    // Never overwrite a config we couldn't save a copy of
+    backups::create(path).map_err(|e| {
+        AppError::new(ErrorKind::ConfigWrite, "Failed to back up config before writing")
+            .with_path(&backups::backup_dir(path))
+            .with_source(e)
+    })?;
+    write_atomic(path, content.as_bytes()).map_err(write_error)?;
+    if let Ok(mut own) = OWN_WRITES.lock() {
+        own.insert(path.to_path_buf(), doc.clone());
+    }
+    Ok(())
+}
+
+/// Whether `doc` is exactly what this app last wrote to `path`.
+pub fn is_own_write(path: &Path, doc: &Value) -> bool {
+    OWN_WRITES
+        .lock()
+        .map(|own| own.get(path) == Some(doc))
+        .unwrap_or(false)
+}
+
+/// Leaf-level differences between two config documents, secrets redacted.
+pub fn diff(old: &Value, new: &Value) -> Vec<ConfigChange> {
+    let mut out = Vec::new();
+    diff_into("", Some(old), Some(new), &mut out);
+    out
+}
+
+fn diff_into(path: &str, old: Option<&Value>, new: Option<&Value>, out: &mut Vec<ConfigChange>) {
+    if let (Some(Value::Object(old)), Some(Value::Object(new))) = (old, new) {
+        let keys = old.keys().chain(new.keys().filter(|k| !old.contains_key(*k)));
+        for key in keys {
+            let child = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            diff_into(&child, old.get(key), new.get(key), out);
+        }
+        return;
+    }
+    if old == new {
+        return;
+    }
+
+    let key = path.rsplit('.').next().unwrap_or(path);
+    let redact = |v: Option<&Value>| match v {
+        Some(_) if SECRET_KEYS.contains(&key) => Some(Value::from("<redacted>")),
+        v => v.map(redact_secrets),
+    };
+    out.push(ConfigChange {
+        path: path.to_string(),
+        old: redact(old),
+        new: redact(new),
+    });
+}
+
+/// `value` with secrets at any depth redacted, for sections that were added,
+/// removed or replaced as a whole.
+fn redact_secrets(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let v = if SECRET_KEYS.contains(&k.as_str()) {
+                        Value::from("<redacted>")
+                    } else {
+                        redact_secrets(v)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact_secrets).collect()),
+        v => v.clone(),
+    }
+}
+
+/// Suffix of `write_atomic`'s temp files.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write to a temp file in the same directory, fsync, then rename over
+/// `path`, so a crash never leaves a half-written config behind.
+pub fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    // Unique per call, so concurrent writers never share a temp file
+    let seq = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let tmp = dir.join(format!(".{}.tmp-{}-{}", name, std::process::id(), seq));
+
+    // The config holds API keys: keep existing permissions, default to 0600
+    let mode = fs::metadata(path)
+        .map(|m| m.permissions().mode())
+        .unwrap_or(0o600);
+
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(mode)
+            .open(&tmp)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        File::open(dir)?.sync_all()
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Create the config of a new gateway instance at `path`, starting from the
+/// default config (providers, channels, ...) with its own port and token.
+#[tracing::instrument(err)]
+pub fn create_instance_config(path: &Path, port: u16) -> AppResult<OpenClawConfig> {
+    if path.exists() {
+        return load_config_from(path);
+    }
+
+    let mut doc = match config_path().filter(|p| p.exists()) {
+        Some(default) => read_document(&default)?,
+        None => Value::Object(Map::new()),
+    };
+    merge_patch(
+        &mut doc,
+        &serde_json::json!({
+            "gateway": { "port": port, "auth": { "token": crate::setup::generate_token() } }
+        }),
+    );
+    update_config_at(path, &doc)
+}
+
+/// Rewrite `gateway.port` in openclaw.json, keeping every other key.
+#[tracing::instrument(err)]
+pub fn set_gateway_port(port: u16) -> AppResult<()> {
+    update_config(&serde_json::json!({ "gateway": { "port": port } })).map(|_| ())
+}
+
+/// Validate `update` against the current config and apply it.
+#[tracing::instrument(err)]
+pub fn update_gateway(update: &GatewayUpdate) -> AppResult<OpenClawConfig> {
+    let current = load_config()?;
+    let invalid = |msg: String| AppError::new(ErrorKind::InvalidInput, msg);
+
+    let mut gateway = Map::new();
+    let mut auth = Map::new();
+
+    if let Some(port) = update.port {
+        if port < 1024 {
+            return Err(invalid(format!(
+                "Port {} is privileged, choose a port between 1024 and 65535",
+                port
+            )));
+        }
+        gateway.insert("port".into(), port.into());
+    }
+
+    if let Some(bind) = &update.bind {
+        let bind = bind.trim();
+        if bind != "loopback" && bind != "lan" && bind.parse::<IpAddr>().is_err() {
+            return Err(invalid(format!(
+                "Bind must be \"loopback\", \"lan\" or an IP address, got \"{}\"",
+                bind
+            )));
+        }
+        gateway.insert("bind".into(), bind.into());
+    }
+
+    if let Some(mode) = &update.auth_mode {
+        if !AUTH_MODES.contains(&mode.as_str()) {
+            return Err(invalid(format!(
+                "Auth mode must be one of {}, got \"{}\"",
+                AUTH_MODES.join(", "),
+                mode
+            )));
+        }
+        auth.insert("mode".into(), mode.as_str().into());
+    }
+
+    let mode = update.auth_mode.as_deref().unwrap_or(&current.gateway.auth.mode);
+    let needs_token = mode == "token" && current.gateway.auth.token.is_empty();
+    if update.rotate_token || needs_token {
+        auth.insert("token".into(), crate::setup::generate_token().into());
+    }
+
+    if !auth.is_empty() {
+        gateway.insert("auth".into(), Value::Object(auth));
+    }
+    if gateway.is_empty() {
+        return Ok(current);
+    }
+
+    update_config(&serde_json::json!({ "gateway": gateway }))
+}
+
 impl GatewayConfig {
+    /// Explicit address from `bind`, if it isn't one of the named modes.
+    fn bind_addr(&self) -> Option<IpAddr> {
+        self.bind.trim().parse().ok()
+    }
+
+    /// Whether the gateway is reachable from other machines.
+    pub fn is_exposed(&self) -> bool {
+        match self.bind.as_str() {
+            "loopback" => false,
+            "lan" => true,
+            _ => self.bind_addr().is_some_and(|a| !a.is_loopback()),
+        }
+    }
+
+    /// URL this machine uses to talk to the gateway.
     pub fn base_url(&self) -> String {
-        format!("http://127.0.0.1:{}", self.port)
+        let host = match self.bind_addr() {
+            Some(addr) if !addr.is_unspecified() => addr,
+            // loopback, lan and 0.0.0.0/:: all accept connections on 127.0.0.1
+            _ => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        };
+        url_for(host, self.port)
     }
 
     pub fn full_url(&self) -> String {
-        if self.auth.token.is_empty() {
-            self.base_url()
-        } else {
-            format!("{}/#token={}", self.base_url(), self.auth.token)
+        with_token(self.base_url(), &self.auth.token)
+    }
+
+    /// URL other devices on the network can use, or `None` for loopback binds.
+    pub fn lan_url(&self) -> Option<String> {
+        if !self.is_exposed() {
+            return None;
+        }
+        let host = match self.bind_addr() {
+            Some(addr) if !addr.is_unspecified() => addr,
+            _ => primary_lan_addr()?,
+        };
+        Some(url_for(host, self.port))
+    }
+
+    /// `lan_url` with the token, suitable for encoding in a QR code.
+    pub fn lan_full_url(&self) -> Option<String> {
+        self.lan_url().map(|url| with_token(url, &self.auth.token))
+    }
+
+    /// Problems with the current combination of bind and auth settings.
+    pub fn security_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.is_exposed() && (self.auth.mode == "none" || self.auth.token.is_empty()) {
+            warnings.push(format!(
+                "The gateway is bound to \"{}\" without authentication; anyone on the network can use it",
+                self.bind
+            ));
+        }
+        warnings
+    }
+}
+
+fn url_for(host: IpAddr, port: u16) -> String {
+    match host {
+        IpAddr::V4(v4) => format!("http://{}:{}", v4, port),
+        IpAddr::V6(v6) => format!("http://[{}]:{}", v6, port),
+    }
+}
+
+fn with_token(url: String, token: &str) -> String {
+    if token.is_empty() {
+        url
+    } else {
+        format!("{}/#token={}", url, token)
+    }
+}
+
+/// Address of the interface holding the default route. Connecting a UDP
+/// socket only selects a route; no packet is sent.
+pub fn primary_lan_addr() -> Option<IpAddr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9)).ok()?;
+    let addr = socket.local_addr().ok()?.ip();
+    (!addr.is_unspecified() && !addr.is_loopback()).then_some(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "openclaw-config-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn merge_patch_null_deletes_keys() {
+        let mut doc = json!({
+            "gateway": { "port": 18789, "bind": "lan", "extra": 1 },
+            "proxy": { "url": "http://proxy:3128" },
+            "defaultModel": "m"
+        });
+        merge_patch(
+            &mut doc,
+            &json!({ "gateway": { "bind": null, "port": 18800 }, "proxy": null, "new": true }),
+        );
+        assert_eq!(
+            doc,
+            json!({ "gateway": { "port": 18800, "extra": 1 }, "defaultModel": "m", "new": true })
+        );
+        // Remaining keys keep their order, new ones are appended
+        let keys: Vec<&String> = doc.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["gateway", "defaultModel", "new"]);
+    }
+
+    #[test]
+    fn merge_patch_replaces_non_objects() {
+        let mut doc = json!({ "list": [1, 2], "scalar": 1 });
+        merge_patch(
+            &mut doc,
+            &json!({ "list": [3], "scalar": { "a": null, "b": 2 } }),
+        );
+        assert_eq!(doc, json!({ "list": [3], "scalar": { "b": 2 } }));
+
+        let mut doc = json!({ "a": 1 });
+        merge_patch(&mut doc, &json!([1]));
+        assert_eq!(doc, json!([1]));
+    }
+
+    #[test]
+    fn diff_redacts_secrets() {
+        let old = json!({
+            "gateway": { "port": 18789, "auth": { "mode": "token", "token": "old" } },
+            "proxy": { "url": "http://proxy:3128", "password": "pw" }
+        });
+        let new = json!({
+            "gateway": { "port": 18800, "auth": { "mode": "token", "token": "new" } },
+            "proxy": { "url": "http://proxy:3128" },
+            "providers": { "openai": { "apiKey": "sk-1" } }
+        });
+        let changes: Vec<(String, Option<Value>, Option<Value>)> = diff(&old, &new)
+            .into_iter()
+            .map(|c| (c.path, c.old, c.new))
+            .collect();
+        let redacted = Some(json!("<redacted>"));
+        assert_eq!(
+            changes,
+            [
+                (
+                    "gateway.port".to_string(),
+                    Some(json!(18789)),
+                    Some(json!(18800))
+                ),
+                (
+                    "gateway.auth.token".to_string(),
+                    redacted.clone(),
+                    redacted.clone()
+                ),
+                ("proxy.password".to_string(), redacted.clone(), None),
+                (
+                    "providers".to_string(),
+                    None,
+                    Some(json!({ "openai": { "apiKey": "<redacted>" } }))
+                ),
+            ]
+        );
+        assert!(diff(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn diff_redacts_secrets_in_replaced_values() {
+        let old = json!({ "channels": { "telegram": "legacy" } });
+        let new = json!({ "channels": { "telegram": { "token": "123:abc", "enabled": true } } });
+        let changes = diff(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "channels.telegram");
+        assert_eq!(
+            changes[0].new,
+            Some(json!({ "token": "<redacted>", "enabled": true }))
+        );
+
+        let removed = diff(&new, &json!({}));
+        assert_eq!(
+            removed[0].old,
+            Some(json!({ "telegram": { "token": "<redacted>", "enabled": true } }))
+        );
+    }
+
+    #[test]
+    fn gateway_and_proxy_changes_need_a_restart() {
+        let change = |path: &str| ConfigChange {
+            path: path.to_string(),
+            old: None,
+            new: None,
+        };
+        for path in [
+            "gateway",
+            "gateway.port",
+            "gateway.auth.token",
+            "proxy",
+            "proxy.url",
+        ] {
+            assert!(change(path).affects_gateway(), "{}", path);
         }
+        for path in [
+            "gatewayExtra",
+            "proxyless",
+            "providers.x.apiKey",
+            "defaultModel",
+        ] {
+            assert!(!change(path).affects_gateway(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn write_atomic_keeps_permissions_and_leaves_no_temp_files() {
+        let dir = scratch("atomic");
+        let path = dir.join("openclaw.json");
+
+        write_atomic(&path, b"{}").unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        write_atomic(&path, b"{\"a\": 1}").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"a\": 1}");
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o640
+        );
+
+        let names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["openclaw.json"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn update_keeps_comments_and_records_own_write() {
+        let dir = scratch("update");
+        let path = dir.join("openclaw.json");
+        fs::write(
+            &path,
+            "{\n  // managed by hand\n  gateway: { port: 18789, bind: 'lan' },\n}\n",
+        )
+        .unwrap();
+
+        let cfg = update_config_at(&path, &json!({ "gateway": { "bind": null } })).unwrap();
+        assert_eq!(cfg.gateway.bind, "loopback");
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("// managed by hand"));
+        assert!(!text.contains("lan"));
+        assert!(is_own_write(&path, &read_document(&path).unwrap()));
+
+        // Invalid results are refused and the file is left alone
+        assert!(update_config_at(&path, &json!({ "gateway": null })).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+        let _ = fs::remove_dir_all(&dir);
     }
 }
@@ -0,0 +1,146 @@
+use reqwest::Url;
+use std::net::IpAddr;
+
+use crate::config::{self, GatewayConfig};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::instance::{self, DEFAULT_INSTANCE};
+use crate::settings::{self, ConnectionSettings};
+
+/// The gateway the app talks to: one it runs locally from openclaw.json,
+/// or a remote one given by URL and token in the desktop settings.
+#[derive(Debug, Clone)]
+pub enum Connection {
+    Local(GatewayConfig),
+    Remote { url: Url, token: String },
+}
+
+/// Resolve the active connection from the desktop settings.
+pub fn current() -> AppResult<Connection> {
+    Connection::from_settings(settings::load_settings().active_connection())
+}
+
+/// Instance whose gateway the main window uses: the one the active local
+/// connection refers to, otherwise the default.
+pub fn main_instance() -> String {
+    match settings::load_settings().active_connection() {
+        ConnectionSettings::Local {
+            instance: Some(id), ..
+        } => id,
+        _ => DEFAULT_INSTANCE.to_string(),
+    }
+}
+
+/// Reject connection settings that can't be resolved before saving them.
+pub fn validate(settings: &ConnectionSettings) -> AppResult<()> {
+    match settings {
+        ConnectionSettings::Remote { url, .. } => parse_remote_url(url).map(drop),
+        ConnectionSettings::Local {
+            instance: Some(_),
+            config_path: Some(_),
+            ..
+        } => Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "A local connection uses either an instance or a config file, not both",
+        )),
+        ConnectionSettings::Local {
+            instance: Some(id), ..
+        } => instance::get(id).map(drop),
+        ConnectionSettings::Local { .. } => Ok(()),
+    }
+}
+
+/// Accept only absolute http(s) URLs for remote gateways.
+pub fn parse_remote_url(url: &str) -> AppResult<Url> {
+    let invalid = |msg: String| AppError::new(ErrorKind::InvalidInput, msg);
+
+    let parsed = Url::parse(url.trim())
+        .map_err(|e| invalid(format!("Invalid gateway URL \"{}\"", url)).with_source(e))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host().is_none() {
+        return Err(invalid(format!(
+            "Gateway URL must start with http:// or https://, got \"{}\"",
+            url
+        )));
+    }
+    Ok(parsed)
+}
+
+impl Connection {
+    pub fn from_settings(settings: ConnectionSettings) -> AppResult<Connection> {
+        match settings {
+            ConnectionSettings::Local {
+                instance,
+                config_path,
+                port,
+                token,
+            } => {
+                let config = match (instance, config_path) {
+                    (Some(id), _) => config::load_config_from(&instance::get(&id)?.config_path)?,
+                    (None, Some(path)) => config::load_config_from(&path)?,
+                    (None, None) => config::load_config()?,
+                };
+                let mut gw = config.gateway;
+                if let Some(port) = port {
+                    gw.port = port;
+                }
+                if let Some(token) = token {
+                    gw.auth.token = token;
+                }
+                Ok(Connection::Local(gw))
+            }
+            ConnectionSettings::Remote { url, token } => Ok(Connection::Remote {
+                url: parse_remote_url(&url)?,
+                token,
+            }),
+        }
+    }
+
+    pub fn base_url(&self) -> String {
+        match self {
+            Connection::Local(gw) => gw.base_url(),
+            Connection::Remote { url, .. } => url.as_str().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn full_url(&self) -> String {
+        match self {
+            Connection::Local(gw) => gw.full_url(),
+            Connection::Remote { token, .. } if token.is_empty() => self.base_url(),
+            Connection::Remote { token, .. } => format!("{}/#token={}", self.base_url(), token),
+        }
+    }
+
+    pub fn token(&self) -> &str {
+        match self {
+            Connection::Local(gw) => &gw.auth.token,
+            Connection::Remote { token, .. } => token,
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        match self {
+            Connection::Local(gw) => gw.port,
+            Connection::Remote { url, .. } => url.port_or_known_default().unwrap_or(80),
+        }
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        match self {
+            Connection::Local(gw) => gw.security_warnings(),
+            Connection::Remote { url, .. } if url.scheme() == "http" && !is_local_host(url) => {
+                vec![format!(
+                    "{} is not using https; the gateway token is sent unencrypted",
+                    url.host_str().unwrap_or_default()
+                )]
+            }
+            Connection::Remote { .. } => Vec::new(),
+        }
+    }
+}
+
+fn is_local_host(url: &Url) -> bool {
+    let host = url
+        .host_str()
+        .unwrap_or_default()
+        .trim_matches(|c| c == '[' || c == ']');
+    host == "localhost" || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
@@ -0,0 +1,564 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::backups;
+use crate::config::{self, ConfigChange, OpenClawConfig, AUTH_MODES, DEFAULT_PORT};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::json5::{self, NodeKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The gateway won't start, or won't work, until this is fixed.
+    Error,
+    Warning,
+}
+
+/// How a diagnostic can be repaired automatically.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FixAction {
+    /// Apply a JSON merge patch to the config.
+    Patch { patch: Value },
+    /// Replace the whole file with a backup that is known to be valid.
+    RestoreBackup { id: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Fix {
+    pub description: String,
+    pub action: FixAction,
+}
+
+/// One problem in openclaw.json, located by JSON path and line/column.
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    /// `<code>:<path>`, used to ask for the fix to be applied.
+    pub id: String,
+    pub severity: Severity,
+    /// Stable machine-readable kind, e.g. `missing_token`.
+    pub code: String,
+    /// Dotted path such as `gateway.auth.token`; empty for the whole file.
+    /// Keys that contain a dot or a quote are written as JSON strings, as
+    /// in `channels."my.bot".token`.
+    pub path: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<Fix>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub path: PathBuf,
+    /// No diagnostic of `Error` severity.
+    pub ok: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Collects diagnostics, filling in positions from the key index.
+struct Checker<'a> {
+    keys: &'a [KeyPos],
+    out: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn push(&mut self, severity: Severity, code: &str, path: &[&str], message: String, fix: Option<Fix>) {
+        let path: Vec<String> = path.iter().map(|s| s.to_string()).collect();
+        let diagnostic = diagnostic(self.keys, severity, code, &path, message, fix);
+        self.out.push(diagnostic);
+    }
+}
+
+fn diagnostic(
+    keys: &[KeyPos],
+    severity: Severity,
+    code: &str,
+    path: &[String],
+    message: String,
+    fix: Option<Fix>,
+) -> Diagnostic {
+    let (line, column) = match locate(keys, path) {
+        Some((line, column)) => (Some(line), Some(column)),
+        None => (None, None),
+    };
+    let path = dotted(path);
+    Diagnostic {
+        id: format!("{}:{}", code, path),
+        severity,
+        code: code.to_string(),
+        path,
+        message,
+        line,
+        column,
+        fix,
+    }
+}
+
+/// `segments` joined with dots. Keys that are empty or contain a dot, quote
+/// or backslash are quoted, so no two paths look the same.
+fn dotted(segments: &[String]) -> String {
+    segments
+        .iter()
+        .map(|s| {
+            if s.is_empty() || s.contains(['.', '"', '\\']) {
+                Value::from(s.as_str()).to_string()
+            } else {
+                s.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn patch_fix(description: impl Into<String>, patch: Value) -> Option<Fix> {
+    Some(Fix {
+        description: description.into(),
+        action: FixAction::Patch { patch },
+    })
+}
+
+/// Check the config at `path`: syntax first, then the schema, then
+/// semantic rules the gateway would otherwise fail on at runtime.
+pub fn check(path: &Path) -> AppResult<DoctorReport> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        let kind = if e.kind() == std::io::ErrorKind::NotFound {
+            ErrorKind::ConfigMissing
+        } else {
+            ErrorKind::ConfigRead
+        };
+        AppError::new(kind, format!("Failed to read {}", path.display()))
+            .with_path(path)
+            .with_source(e)
+    })?;
+
+    let parsed = json5::parse_spanned(&content);
+    let keys = match &parsed {
+        Ok((_, root)) => index_keys(&content, root),
+        Err(_) => Vec::new(),
+    };
+    let mut checker = Checker {
+        keys: &keys,
+        out: Vec::new(),
+    };
+
+    match parsed {
+        Err(e) => {
+            let fix = newest_valid_backup(path).map(|backup| Fix {
+                description: format!("Restore backup {} ({})", backup.id, backup.created),
+                action: FixAction::RestoreBackup { id: backup.id },
+            });
+            checker.out.push(Diagnostic {
+                id: "syntax:".to_string(),
+                severity: Severity::Error,
+                code: "syntax".to_string(),
+                path: String::new(),
+                message: e.message,
+                line: Some(e.line),
+                column: Some(e.column),
+                fix,
+            });
+        }
+        Ok((doc, _)) => match serde_json::from_value::<OpenClawConfig>(doc.clone()) {
+            Err(e) => {
+                let message = e.to_string();
+                let path = schema_error_path(&doc);
+                let bad_port = match path.as_slice() {
+                    [gateway, port] => gateway == "gateway" && port == "port",
+                    [gateway] => {
+                        gateway == "gateway" && message.starts_with("missing field `port`")
+                    }
+                    _ => false,
+                };
+                let fix = if bad_port {
+                    patch_fix(
+                        format!("Use the default port {}", DEFAULT_PORT),
+                        json!({ "gateway": { "port": DEFAULT_PORT } }),
+                    )
+                } else {
+                    None
+                };
+                let diagnostic = diagnostic(&keys, Severity::Error, "schema", &path, message, fix);
+                checker.out.push(diagnostic);
+            }
+            Ok(cfg) => check_semantics(&mut checker, &cfg, &doc),
+        },
+    }
+
+    let diagnostics = checker.out;
+    Ok(DoctorReport {
+        path: path.to_path_buf(),
+        ok: !diagnostics.iter().any(|d| d.severity == Severity::Error),
+        diagnostics,
+    })
+}
+
+fn check_semantics(c: &mut Checker, cfg: &OpenClawConfig, doc: &Value) {
+    let gw = &cfg.gateway;
+
+    if gw.port < 1024 {
+        // First free port from the default one on
+        let port = crate::gateway::find_free_port(DEFAULT_PORT - 1).unwrap_or(DEFAULT_PORT);
+        c.push(
+            Severity::Error,
+            "port_range",
+            &["gateway", "port"],
+            format!(
+                "Port {} is privileged or invalid, the gateway needs a port between 1024 and 65535",
+                gw.port
+            ),
+            patch_fix(format!("Use port {}", port), json!({ "gateway": { "port": port } })),
+        );
+    }
+
+    let bind = gw.bind.trim();
+    if bind != "loopback" && bind != "lan" && bind.parse::<IpAddr>().is_err() {
+        c.push(
+            Severity::Error,
+            "invalid_bind",
+            &["gateway", "bind"],
+            format!("Bind must be \"loopback\", \"lan\" or an IP address, got \"{}\"", gw.bind),
+            patch_fix("Bind to loopback", json!({ "gateway": { "bind": "loopback" } })),
+        );
+    }
+
+    let mode = gw.auth.mode.as_str();
+    if !mode.is_empty() && !AUTH_MODES.contains(&mode) {
+        c.push(
+            Severity::Error,
+            "invalid_auth_mode",
+            &["gateway", "auth", "mode"],
+            format!("Auth mode must be one of {}, got \"{}\"", AUTH_MODES.join(", "), mode),
+            patch_fix("Use token auth", json!({ "gateway": { "auth": { "mode": "token" } } })),
+        );
+    }
+
+    if mode == "token" && gw.auth.token.is_empty() {
+        c.push(
+            Severity::Error,
+            "missing_token",
+            &["gateway", "auth", "token"],
+            "Token auth is enabled but no token is set".to_string(),
+            patch_fix(
+                "Generate a new token",
+                json!({ "gateway": { "auth": { "token": crate::setup::generate_token() } } }),
+            ),
+        );
+    } else if gw.is_exposed() && (mode == "none" || gw.auth.token.is_empty()) {
+        c.push(
+            Severity::Warning,
+            "exposed_without_auth",
+            &["gateway", "bind"],
+            format!("The gateway is bound to \"{}\" without authentication", gw.bind),
+            patch_fix(
+                "Require a token",
+                json!({ "gateway": { "auth": { "mode": "token", "token": crate::setup::generate_token() } } }),
+            ),
+        );
+    }
+
+    if let Some(default) = &cfg.default_provider {
+        if !cfg.providers.contains_key(default) {
+            let fix = match cfg.providers.keys().next() {
+                Some(first) => patch_fix(
+                    format!("Use \"{}\" as the default provider", first),
+                    json!({ "defaultProvider": first }),
+                ),
+                None => patch_fix("Remove the default provider", json!({ "defaultProvider": null })),
+            };
+            c.push(
+                Severity::Error,
+                "unknown_provider",
+                &["defaultProvider"],
+                format!("Default provider \"{}\" is not configured under providers", default),
+                fix,
+            );
+        }
+    }
+
+    for (name, provider) in &cfg.providers {
+        let has_key = provider.api_key.as_deref().is_some_and(|k| !k.trim().is_empty());
+        // Providers with a custom base URL (local models) often need no key
+        if !has_key && provider.base_url.is_none() {
+            c.push(
+                Severity::Warning,
+                "missing_api_key",
+                &["providers", name, "apiKey"],
+                format!("Provider \"{}\" has no API key", name),
+                None,
+            );
+        }
+    }
+
+    for (name, channel) in &cfg.channels {
+        let enabled = channel.enabled.unwrap_or(true);
+        let has_token = channel.token.as_deref().is_some_and(|t| !t.trim().is_empty());
+        // Some channels keep their credentials elsewhere; only flag an explicit empty token
+        if enabled && !has_token && doc["channels"][name].get("token").is_some() {
+            c.push(
+                Severity::Warning,
+                "missing_channel_token",
+                &["channels", name, "token"],
+                format!("Channel \"{}\" is enabled but has an empty token", name),
+                patch_fix(
+                    format!("Disable channel \"{}\"", name),
+                    json!({ "channels": { name: { "enabled": false } } }),
+                ),
+            );
+        }
+    }
+}
+
+fn newest_valid_backup(path: &Path) -> Option<backups::ConfigBackup> {
+    backups::list(path).into_iter().find(|b| {
+        config::read_document(&b.path)
+            .is_ok_and(|doc| serde_json::from_value::<OpenClawConfig>(doc).is_ok())
+    })
+}
+
+/// Apply the fixes of the diagnostics in `ids`, then check again.
+/// Returns the new report and whether settings the gateway reads at
+/// startup changed.
+#[tracing::instrument(err)]
+pub fn apply_fixes(path: &Path, ids: &[String]) -> AppResult<(DoctorReport, bool)> {
+    let report = check(path)?;
+    let wanted: BTreeSet<&str> = ids.iter().map(String::as_str).collect();
+    let fixes: Vec<FixAction> = report
+        .diagnostics
+        .into_iter()
+        .filter(|d| wanted.contains(d.id.as_str()))
+        .filter_map(|d| d.fix.map(|f| f.action))
+        .collect();
+    if fixes.is_empty() {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "None of the selected problems has an automatic fix",
+        ));
+    }
+
+    let before = config::read_document(path).ok();
+    for fix in fixes {
+        match fix {
+            // A restore replaces the whole file; other fixes no longer apply
+            FixAction::RestoreBackup { id } => {
+                backups::restore(path, &id)?;
+                break;
+            }
+            FixAction::Patch { patch } => {
+                config::update_config_at(path, &patch)?;
+            }
+        }
+    }
+    info!(path = %path.display(), fixes = ids.len(), "applied config fixes");
+
+    let after = config::read_document(path).ok();
+    let gateway_changed = match (&before, &after) {
+        (Some(before), Some(after)) => config::diff(before, after)
+            .iter()
+            .any(ConfigChange::affects_gateway),
+        // The file didn't parse before, so the gateway couldn't have used it
+        _ => true,
+    };
+    Ok((check(path)?, gateway_changed))
+}
+
+/// Position of an object key in the source, by key path.
+struct KeyPos {
+    path: Vec<String>,
+    line: usize,
+    column: usize,
+}
+
+/// Position of `path`, or of its nearest parent present in the file.
+fn locate(keys: &[KeyPos], path: &[String]) -> Option<(usize, usize)> {
+    (1..=path.len())
+        .rev()
+        .find_map(|n| keys.iter().find(|k| k.path == path[..n]))
+        .map(|k| (k.line, k.column))
+}
+
+/// Path of the last key at or before `line`/`column`.
+fn path_at(keys: &[KeyPos], line: usize, column: usize) -> Vec<String> {
+    keys.iter()
+        .take_while(|k| (k.line, k.column) <= (line, column))
+        .last()
+        .map(|k| k.path.clone())
+        .unwrap_or_default()
+}
+
+/// Path of the innermost object whose braces enclose `offset`.
+fn object_at(node: &json5::Node, offset: usize) -> Vec<String> {
+    let NodeKind::Object(members) = &node.kind else {
+        return Vec::new();
+    };
+    for member in members {
+        let value = &member.value;
+        if matches!(value.kind, NodeKind::Object(_)) && value.start < offset && offset <= value.end
+        {
+            let mut path = vec![member.key.clone()];
+            path.extend(object_at(value, offset));
+            return path;
+        }
+    }
+    Vec::new()
+}
+
+/// Record the position of every object key, in document order.
+fn index_keys(text: &str, root: &json5::Node) -> Vec<KeyPos> {
+    let mut keys = Vec::new();
+    index_node(text, root, &[], &mut keys);
+    keys
+}
+
+fn index_node(text: &str, node: &json5::Node, path: &[String], keys: &mut Vec<KeyPos>) {
+    let NodeKind::Object(members) = &node.kind else {
+        return;
+    };
+    for member in members {
+        let mut child = path.to_vec();
+        child.push(member.key.clone());
+        let (line, column) = json5::line_col(text, member.key_start);
+        keys.push(KeyPos {
+            path: child.clone(),
+            line,
+            column,
+        });
+        index_node(text, &member.value, &child, keys);
+    }
+}
+
+/// Where serde stops when deserializing `doc` as an `OpenClawConfig`, as a
+/// key path. serde only reports positions when reading text, so this runs
+/// on a strict JSON rendering of the document.
+fn schema_error_path(doc: &Value) -> Vec<String> {
+    let Ok(pretty) = serde_json::to_string_pretty(doc) else {
+        return Vec::new();
+    };
+    let Err(e) = serde_json::from_str::<OpenClawConfig>(&pretty) else {
+        return Vec::new();
+    };
+    let Ok((_, root)) = json5::parse_spanned(&pretty) else {
+        return Vec::new();
+    };
+    // A missing field is reported at the end of the object lacking it, which
+    // is what the path should name; otherwise the last key before where
+    // serde stopped is the offending one
+    if e.to_string().starts_with("missing field") {
+        object_at(&root, offset_of(&pretty, e.line(), e.column()))
+    } else {
+        path_at(&index_keys(&pretty, &root), e.line(), e.column())
+    }
+}
+
+/// Byte offset of a 1-based line and column as serde_json reports them.
+fn offset_of(text: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    line_start + column.saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "openclaw-doctor-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("openclaw.json");
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn missing_field_points_at_the_object_lacking_it() {
+        let doc = json!({ "gateway": { "bind": "lan", "auth": { "mode": "token" } } });
+        assert_eq!(schema_error_path(&doc), ["gateway"]);
+        assert!(schema_error_path(&json!({ "providers": {} })).is_empty());
+        let doc = json!({ "gateway": { "port": "high", "bind": "lan" } });
+        assert_eq!(schema_error_path(&doc), ["gateway", "port"]);
+    }
+
+    #[test]
+    fn missing_port_is_located_and_fixable() {
+        let path = scratch(
+            "missing-port",
+            "{\n  gateway: {\n    bind: 'loopback',\n    auth: { mode: 'none' },\n  },\n}\n",
+        );
+        let report = check(&path).unwrap();
+        let d = &report.diagnostics[0];
+        assert_eq!(
+            (d.id.as_str(), d.path.as_str()),
+            ("schema:gateway", "gateway")
+        );
+        assert_eq!((d.line, d.column), (Some(2), Some(3)));
+        assert!(d.fix.is_some());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn dotted_paths_quote_keys_with_dots() {
+        let path =
+            |segments: &[&str]| dotted(&segments.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+        assert_eq!(path(&["gateway", "auth", "token"]), "gateway.auth.token");
+        assert_eq!(
+            path(&["channels", "my.bot", "token"]),
+            r#"channels."my.bot".token"#
+        );
+        assert_eq!(
+            path(&["channels", "my", "bot", "token"]),
+            "channels.my.bot.token"
+        );
+        assert_eq!(path(&["providers", "a\"b", ""]), r#"providers."a\"b"."""#);
+        assert_eq!(path(&[]), "");
+    }
+
+    #[test]
+    fn fixes_target_the_channel_they_were_reported_for() {
+        let path = scratch(
+            "dotted-channel",
+            r#"{
+  "gateway": { "port": 18789, "auth": { "mode": "token", "token": "t" } },
+  "channels": {
+    "team.alerts": { "token": "" },
+    "team": { "token": "" }
+  }
+}
+"#,
+        );
+        let report = check(&path).unwrap();
+        let ids: Vec<&str> = report.diagnostics.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "missing_channel_token:channels.team.token",
+                r#"missing_channel_token:channels."team.alerts".token"#,
+            ]
+        );
+        assert_eq!(report.diagnostics[0].line, Some(5));
+        assert_eq!(report.diagnostics[1].line, Some(4));
+
+        let (report, _) = apply_fixes(&path, &[ids[1].to_string()]).unwrap();
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].path, "channels.team.token");
+        let doc = config::read_document(&path).unwrap();
+        assert_eq!(doc["channels"]["team.alerts"]["enabled"], json!(false));
+        assert!(doc["channels"]["team"].get("enabled").is_none());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
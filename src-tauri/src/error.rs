@@ -0,0 +1,116 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use crate::json5;
+
+/// Stable, machine-readable error codes. The frontend switches on these, so
+/// existing variants must keep their serialized names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    HomeDirUnknown,
+    ConfigMissing,
+    ConfigRead,
+    ConfigParse,
+    ConfigInvalid,
+    ConfigWrite,
+    SettingsWrite,
+    LogRead,
+    CommandFailed,
+    InvalidInput,
+    PortUnavailable,
+    Window,
+    SecretStore,
+    Internal,
+}
+
+/// Error returned by every Tauri command: a `kind` to react to, a message to
+/// show, and optional details pointing at the cause.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            path: None,
+            line: None,
+            column: None,
+            source: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.display().to_string());
+        self
+    }
+
+    pub fn with_source(mut self, source: impl fmt::Display) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    pub fn with_position(mut self, line: usize, column: usize) -> Self {
+        self.line = Some(line);
+        self.column = Some(column);
+        self
+    }
+
+    pub fn home_dir_unknown() -> Self {
+        Self::new(ErrorKind::HomeDirUnknown, "Could not determine home directory")
+    }
+
+    /// A syntax error in openclaw.json, with the position the parser stopped at.
+    pub fn config_syntax(path: &Path, e: &json5::Error) -> Self {
+        Self::new(
+            ErrorKind::ConfigParse,
+            format!("Failed to parse config at line {}, column {}", e.line, e.column),
+        )
+        .with_path(path)
+        .with_position(e.line, e.column)
+        .with_source(&e.message)
+    }
+
+    /// openclaw.json parses but doesn't have the shape of an `OpenClawConfig`.
+    pub fn config_invalid(path: &Path, e: &serde_json::Error) -> Self {
+        Self::new(ErrorKind::ConfigInvalid, "Config is not a valid OpenClaw config")
+            .with_path(path)
+            .with_source(e)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Internal, message)
+    }
+
+    /// A `spawn_blocking` task panicked or was cancelled.
+    pub fn task_join(e: impl fmt::Display) -> Self {
+        Self::internal("Task join error").with_source(e)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(source) = &self.source {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for AppError {}
@@ -1,88 +1,663 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
 use std::process::{Child, Command};
-use std::sync::Mutex;
-use std::time::Duration;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tracing::{debug, error, info, info_span, warn};
+
+use crate::config::{self, GatewayConfig};
+use crate::connection::{self, Connection};
+use crate::error::AppResult;
+use crate::gateway_env;
+use crate::instance::{self, Instance, DEFAULT_INSTANCE};
+use crate::logs;
+use crate::settings::{self, GatewayPolicy};
+
+/// Number of exits within `CRASH_LOOP_WINDOW` after which we stop restarting.
+const CRASH_LOOP_MAX_EXITS: usize = 5;
+const CRASH_LOOP_WINDOW: Duration = Duration::from_secs(60);
+const BACKOFF_INITIAL: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+const MONITOR_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Lifecycle of a supervised gateway.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum GatewayState {
+    #[default]
+    Stopped,
+    Starting,
+    Running { pid: u32 },
+    /// Using a gateway we didn't spawn; it is never restarted or killed by us.
+    Attached { version: Option<String> },
+    /// Connected to a gateway on another machine.
+    Remote { url: String },
+    Exited { code: Option<i32> },
+    Backoff { attempt: u32, delay_ms: u64 },
+    Failed { reason: String },
+    /// The configured port is held by a process that isn't a healthy gateway.
+    Conflict(PortConflict),
+}
+
+/// Payload of `gateway-lifecycle`: the state plus the instance it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub instance: String,
+    #[serde(flatten)]
+    pub state: GatewayState,
+}
+
+/// Who holds the configured port, as far as `/proc` lets us see.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortConflict {
+    pub port: u16,
+    pub pid: Option<u32>,
+    pub process: Option<String>,
+    pub exe: Option<String>,
+    /// Nearest free port, offered to the user as a replacement.
+    pub suggested_port: Option<u16>,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Supervisor {
+    child: Option<Child>,
+    state: GatewayState,
+    /// Recent exit times, used for backoff and crash-loop detection.
+    exits: VecDeque<Instant>,
+    /// Bumped on every (re)start or shutdown so stale monitors exit.
+    generation: u64,
+}
+
+/// One supervisor per instance id, each with an independent lifecycle.
+static SUPERVISORS: Mutex<BTreeMap<String, Supervisor>> = Mutex::new(BTreeMap::new());
+
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Register the app handle used to emit lifecycle events.
+pub fn init(app: &AppHandle) {
+    let _ = APP_HANDLE.set(app.clone());
+}
+
+/// Run `f` on the supervisor of instance `id`, creating it on first use.
+fn with_supervisor<T>(id: &str, f: impl FnOnce(&mut Supervisor) -> T) -> Option<T> {
+    let mut sups = SUPERVISORS.lock().ok()?;
+    Some(f(sups.entry(id.to_string()).or_default()))
+}
+
+/// State of the gateway the main window uses.
+pub fn current_state() -> GatewayState {
+    state_of(&connection::main_instance())
+}
 
-use crate::config;
+pub fn state_of(id: &str) -> GatewayState {
+    SUPERVISORS
+        .lock()
+        .ok()
+        .and_then(|sups| sups.get(id).map(|sup| sup.state.clone()))
+        .unwrap_or_default()
+}
 
-static GATEWAY_PROCESS: Mutex<Option<Child>> = Mutex::new(None);
+fn set_state(id: &str, state: GatewayState) {
+    info!(instance = id, ?state, "gateway state changed");
+    with_supervisor(id, |sup| sup.state = state.clone());
+    if let Some(app) = APP_HANDLE.get() {
+        let event = LifecycleEvent {
+            instance: id.to_string(),
+            state,
+        };
+        let _ = app.emit("gateway-lifecycle", &event);
+    }
+}
 
-/// Kill the spawned gateway process on app exit.
+/// Stop every spawned gateway on app exit.
 pub fn shutdown() {
-    if let Ok(mut guard) = GATEWAY_PROCESS.lock() {
-        if let Some(ref mut child) = *guard {
-            let _ = child.kill();
-            let _ = child.wait();
-            eprintln!("Gateway process stopped");
+    let ids: Vec<String> = match SUPERVISORS.lock() {
+        Ok(sups) => sups.keys().cloned().collect(),
+        Err(_) => return,
+    };
+    for id in ids {
+        stop(&id);
+    }
+}
+
+/// Stop one instance: SIGTERM its process group, wait for the configured
+/// grace period, then SIGKILL whatever is left.
+pub fn stop(id: &str) {
+    let Some(child) = with_supervisor(id, |sup| {
+        sup.generation += 1;
+        sup.child.take()
+    }) else {
+        return;
+    };
+
+    if let Some(mut child) = child {
+        let grace = Duration::from_secs(settings::load_settings().gateway.shutdown_grace_secs);
+        terminate_group(&mut child, grace);
+        info!(instance = id, "gateway process stopped");
+    }
+    set_state(id, GatewayState::Stopped);
+}
+
+/// Gracefully stop a child spawned as its own process group leader.
+/// Node workers forked by the gateway share the group, so signalling the
+/// group rather than the pid ensures none of them outlive the app.
+fn terminate_group(child: &mut Child, grace: Duration) {
+    let pgid = child.id() as libc::pid_t;
+    signal_group(pgid, libc::SIGTERM);
+
+    let deadline = Instant::now() + grace;
+    let mut reaped = false;
+    while Instant::now() < deadline {
+        if !reaped {
+            reaped = matches!(child.try_wait(), Ok(Some(_)));
+        }
+        // The leader must be reaped first, a zombie still counts as a member.
+        if reaped && !group_alive(pgid) {
+            return;
         }
+        std::thread::sleep(Duration::from_millis(100));
     }
+
+    warn!(
+        pgid,
+        grace_secs = grace.as_secs(),
+        "gateway did not exit within grace period, sending SIGKILL to process group"
+    );
+    signal_group(pgid, libc::SIGKILL);
+    let _ = child.wait();
+}
+
+fn signal_group(pgid: libc::pid_t, signal: libc::c_int) {
+    // SAFETY: kill(2) with a negative pid only signals the given process group.
+    if unsafe { libc::kill(-pgid, signal) } != 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ESRCH) {
+            warn!(pgid, signal, %err, "failed to signal process group");
+        }
+    }
+}
+
+fn group_alive(pgid: libc::pid_t) -> bool {
+    // SAFETY: signal 0 performs only the existence/permission check.
+    unsafe { libc::kill(-pgid, 0) == 0 }
 }
 
-/// Start the gateway, killing any existing instance first.
-/// Always starts fresh to guarantee proxy env vars are set correctly.
+/// Start the default gateway according to the active connection and the
+/// configured `GatewayPolicy`.
+#[tracing::instrument]
 pub fn ensure_started() {
-    let base_url = match config::load_config() {
-        Ok(cfg) => cfg.gateway.base_url(),
+    let conn_settings = settings::load_settings().active_connection();
+    let conn = match Connection::from_settings(conn_settings.clone()) {
+        Ok(conn) => conn,
         Err(e) => {
-            eprintln!("Cannot read config to start gateway: {}", e);
+            error!(error = %e, "cannot read config to start gateway");
             return;
         }
     };
+    for warning in conn.warnings() {
+        warn!("{}", warning);
+    }
 
-    // Always kill existing gateway so we start fresh with correct proxy env.
-    kill_existing_gateway();
+    // Stop supervising our own child before killing, so the old monitor
+    // doesn't mistake the kill for a crash and restart it.
+    stop(DEFAULT_INSTANCE);
+
+    let gw = match conn {
+        Connection::Local(gw) => gw,
+        remote @ Connection::Remote { .. } => {
+            // Nothing to spawn or kill; the health monitor tracks reachability
+            let url = remote.base_url();
+            info!(%url, "using remote gateway");
+            set_state(DEFAULT_INSTANCE, GatewayState::Remote { url });
+            return;
+        }
+    };
 
-    // Resolve openclaw binary from common locations
-    let bin = find_openclaw_bin().unwrap_or_else(|| "openclaw".to_string());
+    if conn_settings.is_external() {
+        attach_external(&gw);
+        return;
+    }
+
+    let id = connection::main_instance();
+    match instance::get(&id) {
+        Ok(inst) => {
+            if !inst.is_default() {
+                stop(&id);
+            }
+            start_local(&inst, gw)
+        }
+        Err(e) => error!(instance = %id, error = %e, "cannot resolve gateway instance"),
+    }
+}
+
+/// Use a local gateway started outside the app, without supervising it.
+fn attach_external(gw: &GatewayConfig) {
+    let base_url = gw.base_url();
+    let state = if !check_health(&base_url) {
+        GatewayState::Failed {
+            reason: format!("No gateway is answering at {}", base_url),
+        }
+    } else {
+        match check_compatibility(&base_url) {
+            Ok(version) => GatewayState::Attached { version },
+            Err(reason) => GatewayState::Failed { reason },
+        }
+    };
+    info!(%base_url, "using gateway started outside the app");
+    set_state(DEFAULT_INSTANCE, state);
+}
+
+/// (Re)start instance `id` from the openclaw.json in its state dir.
+#[tracing::instrument(err)]
+pub fn start_instance(id: &str) -> AppResult<()> {
+    if id == DEFAULT_INSTANCE {
+        ensure_started();
+        return Ok(());
+    }
+
+    let inst = instance::get(id)?;
+    let gw = config::load_config_from(&inst.config_path)?.gateway;
+    for warning in gw.security_warnings() {
+        warn!(instance = id, "{}", warning);
+    }
+
+    stop(id);
+    start_local(&inst, gw);
+    Ok(())
+}
+
+/// Start the extra instances marked `autostart`, each on its own thread.
+pub fn start_autostart_instances() {
+    for inst in instance::all() {
+        if inst.is_default() || !inst.autostart {
+            continue;
+        }
+        std::thread::spawn(move || {
+            if let Err(e) = start_instance(&inst.id) {
+                error!(instance = %inst.id, error = %e, "failed to start gateway instance");
+            }
+        });
+    }
+}
+
+/// Apply the gateway policy for `inst`, then spawn and supervise it.
+fn start_local(inst: &Instance, gw: GatewayConfig) {
+    let id = inst.id.as_str();
+    let (base_url, port) = (gw.base_url(), gw.port);
+
+    let policy = settings::load_settings().gateway.policy;
+    match policy {
+        GatewayPolicy::Attach if check_health(&base_url) => {
+            match check_compatibility(&base_url) {
+                Ok(version) => {
+                    info!(instance = id, %base_url, "attaching to running gateway");
+                    set_state(id, GatewayState::Attached { version });
+                }
+                Err(reason) => set_state(id, GatewayState::Failed { reason }),
+            }
+            return;
+        }
+        GatewayPolicy::Managed if check_health(&base_url) => {
+            set_state(
+                id,
+                GatewayState::Failed {
+                    reason: format!(
+                        "Another gateway is already serving {}; switch the gateway policy to \"attach\" or \"replace\"",
+                        base_url
+                    ),
+                },
+            );
+            return;
+        }
+        // Kill existing gateway so we start fresh with correct proxy env.
+        // Killing by name would take our other instances down too, so only
+        // do that while the default instance is the only one we run.
+        GatewayPolicy::Replace if inst.is_default() && !others_running(id) => {
+            kill_existing_gateway()
+        }
+        GatewayPolicy::Replace => kill_port_owner(port),
+        _ => {}
+    }
+
+    // Spawning onto a busy port would only time out in wait_until_healthy.
+    if let Some(conflict) = check_port_conflict(port) {
+        warn!(instance = id, port, pid = conflict.pid, "{}", conflict.message);
+        set_state(id, GatewayState::Conflict(conflict));
+        return;
+    }
+
+    let Some(generation) = with_supervisor(id, |sup| {
+        sup.generation += 1;
+        sup.exits.clear();
+        sup.generation
+    }) else {
+        return;
+    };
+
+    if spawn_supervised(inst, generation, &base_url) {
+        let inst = inst.clone();
+        let url = base_url.clone();
+        std::thread::spawn(move || monitor(inst, generation, url));
+    }
+}
+
+/// Whether any instance other than `id` has a child we spawned.
+fn others_running(id: &str) -> bool {
+    SUPERVISORS
+        .lock()
+        .map(|sups| sups.iter().any(|(k, sup)| k != id && sup.child.is_some()))
+        .unwrap_or(false)
+}
+
+/// Restart instance `id` after a config change, unless it is one we attached
+/// to: that instance belongs to someone else and is never restarted by us.
+/// Extra instances that were never started stay stopped.
+/// Returns whether a restart was performed.
+pub fn restart_if_managed(id: &str) -> bool {
+    match state_of(id) {
+        GatewayState::Attached { .. } | GatewayState::Remote { .. } => {
+            info!(instance = id, "attached gateway not restarted after config change");
+            false
+        }
+        GatewayState::Stopped if id != DEFAULT_INSTANCE => false,
+        _ => match start_instance(id) {
+            Ok(()) => true,
+            Err(e) => {
+                error!(instance = id, error = %e, "failed to restart gateway");
+                false
+            }
+        },
+    }
+}
+
+/// Spawn the gateway and record it as the supervised child.
+/// Returns false if the spawn failed or the supervisor moved on.
+fn spawn_supervised(inst: &Instance, generation: u64, base_url: &str) -> bool {
+    let id = inst.id.as_str();
+    set_state(id, GatewayState::Starting);
+
+    let child = match spawn_gateway_process(inst) {
+        Ok(child) => child,
+        Err(e) => {
+            error!(instance = id, error = %e, "failed to start gateway");
+            set_state(
+                id,
+                GatewayState::Failed {
+                    reason: format!("Failed to start gateway: {}", e),
+                },
+            );
+            return false;
+        }
+    };
+
+    let pid = child.id();
+    let mut child = Some(child);
+    let current = with_supervisor(id, |sup| {
+        if sup.generation != generation {
+            return false;
+        }
+        sup.child = child.take();
+        true
+    })
+    .unwrap_or(false);
+    if let Some(mut stale) = child {
+        let _ = stale.kill();
+        let _ = stale.wait();
+    }
+    if !current {
+        return false;
+    }
+
+    // Wait for gateway to become ready before UI starts checking
+    let current = || is_current(id, generation);
+    if wait_until_healthy(base_url, 20, current) && current() {
+        set_state(id, GatewayState::Running { pid });
+    }
+    true
+}
+
+fn is_current(id: &str, generation: u64) -> bool {
+    SUPERVISORS
+        .lock()
+        .ok()
+        .and_then(|sups| sups.get(id).map(|sup| sup.generation == generation))
+        .unwrap_or(false)
+}
+
+/// Reap the supervised child and restart it with exponential backoff,
+/// giving up once it exits too often within `CRASH_LOOP_WINDOW`.
+fn monitor(inst: Instance, generation: u64, base_url: String) {
+    let id = inst.id.as_str();
+    loop {
+        std::thread::sleep(MONITOR_INTERVAL);
+
+        let exited = with_supervisor(id, |sup| {
+            if sup.generation != generation {
+                return Err(());
+            }
+            let code = match sup.child.as_mut().map(|c| c.try_wait()) {
+                Some(Ok(Some(status))) => Some(status.code()),
+                Some(Err(e)) => {
+                    warn!(instance = id, error = %e, "failed to poll gateway process");
+                    None
+                }
+                _ => None,
+            };
+            if code.is_some() {
+                sup.child = None;
+            }
+            Ok(code)
+        });
+        let code = match exited {
+            Some(Ok(Some(code))) => code,
+            Some(Ok(None)) => continue,
+            _ => return,
+        };
+
+        set_state(id, GatewayState::Exited { code });
+
+        let Some(attempt) =
+            with_supervisor(id, |sup| record_exit(&mut sup.exits, Instant::now()))
+        else {
+            return;
+        };
+        let delay = backoff_delay(attempt);
+
+        if attempt as usize >= CRASH_LOOP_MAX_EXITS {
+            set_state(
+                id,
+                GatewayState::Failed {
+                    reason: format!(
+                        "Gateway exited {} times within {}s, giving up",
+                        attempt,
+                        CRASH_LOOP_WINDOW.as_secs()
+                    ),
+                },
+            );
+            return;
+        }
+
+        set_state(
+            id,
+            GatewayState::Backoff {
+                attempt,
+                delay_ms: delay.as_millis() as u64,
+            },
+        );
+        std::thread::sleep(delay);
+
+        if !is_current(id, generation) || !spawn_supervised(&inst, generation, &base_url) {
+            return;
+        }
+    }
+}
+
+/// Record an exit at `now`, forget those older than `CRASH_LOOP_WINDOW`
+/// and return how many are left.
+fn record_exit(exits: &mut VecDeque<Instant>, now: Instant) -> u32 {
+    exits.push_back(now);
+    while exits
+        .front()
+        .is_some_and(|t| now.duration_since(*t) > CRASH_LOOP_WINDOW)
+    {
+        exits.pop_front();
+    }
+    exits.len() as u32
+}
 
-    // Build a shell command that exports proxy vars THEN exec's the gateway.
-    // This guarantees ALL descendant processes inherit proxy settings,
-    // even if openclaw internally re-spawns with a clean env.
-    //
-    // For Node.js (undici/fetch), we also set GLOBAL_AGENT_HTTP_PROXY so
-    // libraries like global-agent can intercept requests, and pass
-    // --use-openssl-ca to ensure TLS works through the proxy.
-    let proxy_exports = build_proxy_exports();
-    let shell_cmd = format!("{}exec '{}' gateway run", proxy_exports, bin);
+/// 1s, 2s, 4s, ... capped at `BACKOFF_MAX`.
+fn backoff_delay(attempt: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    BACKOFF_INITIAL.saturating_mul(factor).min(BACKOFF_MAX)
+}
+
+fn spawn_gateway_process(inst: &Instance) -> std::io::Result<Child> {
+    let _span = info_span!("gateway_spawn", instance = %inst.id).entered();
 
-    eprintln!("Shell command: {}", shell_cmd);
+    // Resolve openclaw binary from common locations
+    let bin = find_openclaw_bin().unwrap_or_else(|| "openclaw".to_string());
 
-    // Log to file so we can debug issues
-    let log_path = dirs::home_dir()
-        .map(|h| h.join(".openclaw/desktop-gateway.log"))
-        .unwrap_or_else(|| std::path::PathBuf::from("/tmp/openclaw-gateway.log"));
+    let env = gateway_env::build(inst);
+    debug!(
+        %bin,
+        vars = ?env.vars().map(|(name, _)| name).collect::<Vec<_>>(),
+        "gateway command"
+    );
 
+    // Log stdout and stderr to file so they can be viewed from the app
+    logs::rotate_if_needed(&inst.log_path);
     let log_file = std::fs::OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&log_path);
+        .open(&inst.log_path);
+
+    let mut cmd = Command::new(&bin);
+    cmd.args(["gateway", "run"])
+        .env_clear()
+        .envs(env.vars())
+        // Own process group, so shutdown can signal the gateway and all its workers.
+        .process_group(0);
+
+    match log_file.and_then(|f| Ok((f.try_clone()?, f))) {
+        Ok((out, err)) => {
+            cmd.stdout(std::process::Stdio::from(out));
+            cmd.stderr(std::process::Stdio::from(err));
+        }
+        Err(_) => {
+            cmd.stdout(std::process::Stdio::null());
+            cmd.stderr(std::process::Stdio::null());
+        }
+    }
 
-    let mut cmd = Command::new("bash");
-    cmd.args(["-c", &shell_cmd])
-        .stdout(std::process::Stdio::null());
+    let child = cmd.spawn()?;
+    info!(pid = child.id(), %bin, "gateway spawned");
+    logs::write_session_marker(&inst.log_path, child.id(), &bin, installed_version(&bin).as_deref());
+    Ok(child)
+}
 
-    match log_file {
-        Ok(file) => { cmd.stderr(std::process::Stdio::from(file)); }
-        Err(_) => { cmd.stderr(std::process::Stdio::null()); }
+/// Stop the gateway listening on `port`, leaving other gateways alone.
+/// SIGTERM first, SIGKILL once the grace period has passed. A port held by
+/// anything other than an openclaw gateway is left to `check_port_conflict`.
+fn kill_port_owner(port: u16) {
+    let Some(pid) = find_listening_inodes(port)
+        .into_iter()
+        .find_map(find_pid_by_socket_inode)
+    else {
+        return;
+    };
+    if !is_gateway_process(pid) {
+        info!(pid, port, "port owner is not an openclaw gateway, leaving it running");
+        return;
     }
+    let pid = pid as libc::pid_t;
 
-    match cmd.spawn()
-    {
-        Ok(child) => {
-            eprintln!("Gateway spawned (pid {}) via {}", child.id(), bin);
-            GATEWAY_PROCESS.lock().unwrap().replace(child);
+    signal_pid(pid, libc::SIGTERM);
+    let grace = Duration::from_secs(settings::load_settings().gateway.shutdown_grace_secs);
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline && pid_alive(pid) {
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    if pid_alive(pid) {
+        warn!(pid, port, grace_secs = grace.as_secs(), "still running after grace period, sending SIGKILL");
+        signal_pid(pid, libc::SIGKILL);
+    }
+    // Wait for port to free up
+    std::thread::sleep(Duration::from_secs(1));
+}
 
-            // Wait for gateway to become ready before UI starts checking
-            wait_until_healthy(&base_url, 20);
-        }
-        Err(e) => eprintln!("Failed to start gateway: {}", e),
+/// Whether `pid` runs an openclaw gateway, judged by its executable and
+/// command line.
+fn is_gateway_process(pid: u32) -> bool {
+    let exe = std::fs::read_link(format!("/proc/{}/exe", pid)).ok();
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).unwrap_or_default();
+    looks_like_gateway(exe.as_deref(), &cmdline)
+}
+
+/// Matches `openclaw-gateway` as the executable or an argument, and
+/// `openclaw gateway ...` whether run directly or through node.
+/// `cmdline` is NUL-separated as in /proc/<pid>/cmdline; a process that
+/// rewrote its title may use spaces instead.
+fn looks_like_gateway(exe: Option<&Path>, cmdline: &[u8]) -> bool {
+    let file_name = |s: &str| {
+        Path::new(s)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    if exe.and_then(|p| p.to_str()).map(file_name).as_deref() == Some("openclaw-gateway") {
+        return true;
     }
+
+    let cmdline = String::from_utf8_lossy(cmdline);
+    let args: Vec<String> = cmdline
+        .split(|c: char| c == '\0' || c.is_whitespace())
+        .filter(|a| !a.is_empty())
+        .map(file_name)
+        .collect();
+    args.iter().any(|a| a == "openclaw-gateway")
+        || args
+            .windows(2)
+            .any(|pair| pair[0] == "openclaw" && pair[1] == "gateway")
+}
+
+fn signal_pid(pid: libc::pid_t, signal: libc::c_int) {
+    // SAFETY: kill(2) on a single positive pid only signals that process.
+    unsafe { libc::kill(pid, signal) };
+}
+
+fn pid_alive(pid: libc::pid_t) -> bool {
+    // SAFETY: signal 0 performs only the existence/permission check.
+    unsafe { libc::kill(pid, 0) == 0 }
 }
 
-/// Kill any existing openclaw-gateway process so we can start fresh.
+/// Stop any existing openclaw-gateway process so we can start fresh.
+/// Sends SIGTERM first and only SIGKILLs processes still alive after the grace period.
 fn kill_existing_gateway() {
-    // Kill via pkill
-    let _ = Command::new("pkill").args(["-9", "-f", "openclaw-gateway"]).status();
-    let _ = Command::new("pkill").args(["-9", "-f", "openclaw gateway"]).status();
+    const PATTERNS: [&str; 2] = ["openclaw-gateway", "openclaw gateway"];
+
+    for pattern in PATTERNS {
+        let _ = Command::new("pkill").args(["-TERM", "-f", pattern]).status();
+    }
+
+    let grace = Duration::from_secs(settings::load_settings().gateway.shutdown_grace_secs);
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline && PATTERNS.iter().any(|p| pgrep(p)) {
+        std::thread::sleep(Duration::from_millis(200));
+    }
+
+    for pattern in PATTERNS {
+        if pgrep(pattern) {
+            warn!(pattern, grace_secs = grace.as_secs(), "still running after grace period, sending SIGKILL");
+            let _ = Command::new("pkill").args(["-9", "-f", pattern]).status();
+        }
+    }
+
     // Also stop systemd service if running
     let _ = Command::new("systemctl")
         .args(["--user", "stop", "openclaw-gateway.service"])
@@ -91,16 +666,31 @@ fn kill_existing_gateway() {
     std::thread::sleep(Duration::from_secs(2));
 }
 
+fn pgrep(pattern: &str) -> bool {
+    Command::new("pgrep")
+        .args(["-f", pattern])
+        .stdout(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
 /// Poll health endpoint until gateway is ready, up to `max_secs` seconds.
-fn wait_until_healthy(base_url: &str, max_secs: u32) {
+/// Gives up early once `keep_waiting` returns false, e.g. after `stop()`.
+fn wait_until_healthy(base_url: &str, max_secs: u32, keep_waiting: impl Fn() -> bool) -> bool {
     for i in 0..(max_secs * 2) {
         std::thread::sleep(Duration::from_millis(500));
+        if !keep_waiting() {
+            debug!("stopped waiting for the gateway");
+            return false;
+        }
         if check_health(base_url) {
-            eprintln!("Gateway ready after ~{}ms", (i + 1) * 500);
-            return;
+            info!(elapsed_ms = (i + 1) * 500, "gateway ready");
+            return true;
         }
     }
-    eprintln!("Gateway not ready after {}s, UI will retry", max_secs);
+    warn!(max_secs, "gateway not ready in time, UI will retry");
+    false
 }
 
 fn find_openclaw_bin() -> Option<String> {
@@ -125,72 +715,164 @@ fn find_openclaw_bin() -> Option<String> {
         .map(|s| s.trim().to_string())
 }
 
-/// Build shell export lines for proxy env vars.
-/// Checks current process env first, falls back to GNOME gsettings.
-fn build_proxy_exports() -> String {
-    let mut exports = Vec::new();
-    let no_proxy = "localhost,127.0.0.1,192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,::1";
-
-    // Try current process env first (e.g. launched from terminal)
-    if let Ok(proxy) = std::env::var("HTTP_PROXY").or_else(|_| std::env::var("http_proxy")) {
-        eprintln!("Proxy from env: {}", proxy);
-        exports.push(format!("export HTTP_PROXY='{}' http_proxy='{}' HTTPS_PROXY='{}' https_proxy='{}';",
-            proxy, proxy, proxy, proxy));
-        // Also set GLOBAL_AGENT vars for Node.js global-agent compatibility
-        exports.push(format!("export GLOBAL_AGENT_HTTP_PROXY='{}' GLOBAL_AGENT_HTTPS_PROXY='{}';",
-            proxy, proxy));
-        if let Ok(all) = std::env::var("ALL_PROXY").or_else(|_| std::env::var("all_proxy")) {
-            exports.push(format!("export ALL_PROXY='{}' all_proxy='{}';", all, all));
-        }
-        exports.push(format!("export NO_PROXY='{}' no_proxy='{}';", no_proxy, no_proxy));
-        return exports.join(" ");
+/// Probe `port` on loopback and describe its owner if it is already taken.
+pub fn check_port_conflict(port: u16) -> Option<PortConflict> {
+    if port_is_free(port) {
+        return None;
     }
 
-    // Fall back to GNOME gsettings
-    let mode = gsettings_get("org.gnome.system.proxy", "mode");
-    if mode.as_deref() != Some("manual") {
-        return String::new();
-    }
+    let pid = find_listening_inodes(port)
+        .into_iter()
+        .find_map(find_pid_by_socket_inode);
+    let process = pid.and_then(|p| {
+        std::fs::read_to_string(format!("/proc/{}/comm", p))
+            .ok()
+            .map(|s| s.trim().to_string())
+    });
+    let exe = pid.and_then(|p| {
+        std::fs::read_link(format!("/proc/{}/exe", p))
+            .ok()
+            .map(|path| path.to_string_lossy().into_owned())
+    });
+
+    let message = match (&process, pid) {
+        (Some(name), Some(pid)) => format!("port {} is held by {} pid {}", port, name, pid),
+        (None, Some(pid)) => format!("port {} is held by pid {}", port, pid),
+        // Sockets of other users' processes aren't visible to us
+        _ => format!("port {} is held by a process owned by another user", port),
+    };
+
+    Some(PortConflict {
+        port,
+        pid,
+        process,
+        exe,
+        suggested_port: find_free_port(port),
+        message,
+    })
+}
 
-    let host = gsettings_get("org.gnome.system.proxy.http", "host");
-    let port = gsettings_get("org.gnome.system.proxy.http", "port");
+fn port_is_free(port: u16) -> bool {
+    std::net::TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+/// First free port after `port`, staying close so it's easy to remember.
+pub fn find_free_port(port: u16) -> Option<u16> {
+    find_free_port_excluding(port, &[])
+}
 
-    if let (Some(h), Some(p)) = (host, port) {
-        if !h.is_empty() && p != "0" {
-            let proxy = format!("http://{}:{}/", h, p);
-            eprintln!("Proxy from gsettings: {}", proxy);
-            exports.push(format!("export HTTP_PROXY='{}' http_proxy='{}' HTTPS_PROXY='{}' https_proxy='{}';",
-                proxy, proxy, proxy, proxy));
-            // Also set GLOBAL_AGENT vars for Node.js global-agent compatibility
-            exports.push(format!("export GLOBAL_AGENT_HTTP_PROXY='{}' GLOBAL_AGENT_HTTPS_PROXY='{}';",
-                proxy, proxy));
+/// Like `find_free_port`, also skipping `taken`, e.g. ports of instances
+/// that aren't running right now.
+pub fn find_free_port_excluding(port: u16, taken: &[u16]) -> Option<u16> {
+    (port.saturating_add(1)..=port.saturating_add(100))
+        .find(|p| !taken.contains(p) && port_is_free(*p))
+}
+
+/// Socket inodes listening on `port`, from /proc/net/tcp and tcp6.
+fn find_listening_inodes(port: u16) -> Vec<u64> {
+    const TCP_LISTEN: &str = "0A";
+
+    let mut inodes = Vec::new();
+    for table in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let Ok(content) = std::fs::read_to_string(table) else {
+            continue;
+        };
+        // sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode
+        for line in content.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 10 || fields[3] != TCP_LISTEN {
+                continue;
+            }
+            let local_port = fields[1]
+                .rsplit(':')
+                .next()
+                .and_then(|hex| u16::from_str_radix(hex, 16).ok());
+            if local_port == Some(port) {
+                if let Ok(inode) = fields[9].parse() {
+                    inodes.push(inode);
+                }
+            }
         }
     }
+    inodes
+}
+
+/// Find the process holding a socket by scanning /proc/<pid>/fd symlinks.
+fn find_pid_by_socket_inode(inode: u64) -> Option<u32> {
+    let target = format!("socket:[{}]", inode);
+    std::fs::read_dir("/proc")
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .find(|pid| {
+            std::fs::read_dir(format!("/proc/{}/fd", pid))
+                .map(|fds| {
+                    fds.flatten().any(|fd| {
+                        std::fs::read_link(fd.path())
+                            .map(|link| link.to_string_lossy() == target)
+                            .unwrap_or(false)
+                    })
+                })
+                .unwrap_or(false)
+        })
+}
+
+/// Check that a running gateway runs the same release as the installed CLI.
+/// Returns the running version, if it reports one. `/health` is open, so
+/// this says nothing about whether the gateway accepts our token.
+fn check_compatibility(base_url: &str) -> Result<Option<String>, String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(3))
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    let resp = client
+        .get(format!("{}/health", base_url))
+        .send()
+        .map_err(|e| format!("Failed to query running gateway: {}", e))?;
+
+    let running = resp
+        .text()
+        .ok()
+        .and_then(|body| serde_json::from_str::<serde_json::Value>(&body).ok())
+        .and_then(|v| v.get("version").and_then(|v| v.as_str()).map(str::to_string));
 
-    let socks_host = gsettings_get("org.gnome.system.proxy.socks", "host");
-    let socks_port = gsettings_get("org.gnome.system.proxy.socks", "port");
+    let installed = find_openclaw_bin().and_then(|bin| installed_version(&bin));
 
-    if let (Some(h), Some(p)) = (socks_host, socks_port) {
-        if !h.is_empty() && p != "0" {
-            let socks = format!("socks://{}:{}/", h, p);
-            exports.push(format!("export ALL_PROXY='{}' all_proxy='{}';", socks, socks));
+    if let (Some(running), Some(installed)) = (&running, &installed) {
+        if release_of(running) != release_of(installed) {
+            return Err(format!(
+                "The gateway running at {} is version {}, but the installed openclaw is {}",
+                base_url, running, installed
+            ));
         }
     }
 
-    exports.push(format!("export NO_PROXY='{}' no_proxy='{}';", no_proxy, no_proxy));
-    exports.join(" ")
+    Ok(running)
 }
 
-fn gsettings_get(schema: &str, key: &str) -> Option<String> {
-    Command::new("gsettings")
-        .args(["get", schema, key])
+/// Version reported by `<bin> --version`, without the program name.
+fn installed_version(bin: &str) -> Option<String> {
+    Command::new(bin)
+        .arg("--version")
         .output()
         .ok()
         .filter(|o| o.status.success())
         .and_then(|o| String::from_utf8(o.stdout).ok())
-        .map(|s| s.trim().trim_matches('\'').to_string())
+        .and_then(|s| s.split_whitespace().last().map(str::to_string))
+}
+
+/// "v1.4.2" -> "1.4"
+fn release_of(version: &str) -> String {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(".")
 }
 
+#[tracing::instrument(level = "debug", ret)]
 pub fn check_health(base_url: &str) -> bool {
     let client = reqwest::blocking::Client::builder()
         .timeout(Duration::from_secs(3))
@@ -214,3 +896,63 @@ pub fn check_health(base_url: &str) -> bool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (1..=7).map(|a| backoff_delay(a).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(backoff_delay(0), BACKOFF_INITIAL);
+        assert_eq!(backoff_delay(u32::MAX), BACKOFF_MAX);
+    }
+
+    #[test]
+    fn crash_loop_trips_within_the_window() {
+        let start = Instant::now();
+        let mut exits = VecDeque::new();
+        let attempts: Vec<u32> = (0..CRASH_LOOP_MAX_EXITS as u64)
+            .map(|i| record_exit(&mut exits, start + Duration::from_secs(i)))
+            .collect();
+        assert_eq!(attempts, [1, 2, 3, 4, 5]);
+        assert!(*attempts.last().unwrap() as usize >= CRASH_LOOP_MAX_EXITS);
+    }
+
+    #[test]
+    fn crash_loop_resets_after_the_window() {
+        let start = Instant::now();
+        let mut exits = VecDeque::new();
+        for i in 0..4 {
+            record_exit(&mut exits, start + Duration::from_secs(i));
+        }
+        let later = start + CRASH_LOOP_WINDOW + Duration::from_secs(10);
+        assert_eq!(record_exit(&mut exits, later), 1);
+    }
+
+    #[test]
+    fn only_gateways_count_as_port_owners_to_replace() {
+        let node = Some(Path::new("/usr/bin/node"));
+        assert!(looks_like_gateway(
+            node,
+            b"node\0/home/u/.npm-global/bin/openclaw\0gateway\0run\0"
+        ));
+        assert!(looks_like_gateway(
+            Some(Path::new("/opt/openclaw/openclaw-gateway")),
+            b"/opt/openclaw/openclaw-gateway\0"
+        ));
+        assert!(looks_like_gateway(node, b"openclaw-gateway          \0"));
+
+        assert!(!looks_like_gateway(
+            Some(Path::new("/usr/bin/python3.12")),
+            b"python3\0-m\0http.server\0--directory\0/srv\0"
+        ));
+        assert!(!looks_like_gateway(
+            node,
+            b"node\0/home/u/.npm-global/bin/openclaw\0status\0"
+        ));
+        assert!(!looks_like_gateway(None, b"postgres\0-D\0/var/lib/gateway\0"));
+        assert!(!looks_like_gateway(None, b""));
+    }
+}
@@ -0,0 +1,250 @@
+//! The environment the gateway is spawned with.
+//!
+//! The gateway doesn't inherit the desktop app's environment wholesale.
+//! It gets a fixed set of inherited variables (paths, locale, session),
+//! then the proxy, the Node.js options, the user's extras from the desktop
+//! settings, the instance's own variables and the secrets its config
+//! refers to, each layer overriding the ones before it. The same map is
+//! shown, redacted, by the diagnostics.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use crate::config;
+use crate::instance::Instance;
+use crate::proxy;
+use crate::secrets;
+use crate::settings;
+
+/// Inherited as they are.
+const INHERITED: [&str; 13] = [
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "LANG",
+    "LANGUAGE",
+    "TZ",
+    "TMPDIR",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "NVM_DIR",
+];
+
+/// Inherited when the name starts with one of these.
+const INHERITED_PREFIXES: [&str; 3] = ["LC_", "XDG_", "OPENCLAW_"];
+
+/// Names of variables whose values are hidden in diagnostics.
+const SENSITIVE: [&str; 5] = ["TOKEN", "SECRET", "PASSWORD", "KEY", "AUTH"];
+
+/// The gateway's own names for the state dir and config of the instance.
+/// The desktop's `config::CONFIG_ENV` is never passed on.
+const STATE_DIR_ENV: &str = "OPENCLAW_STATE_DIR";
+const CONFIG_PATH_ENV: &str = "OPENCLAW_CONFIG_PATH";
+
+/// Lets Node.js trust the system CA store, which is where the certificate
+/// of a TLS-intercepting proxy usually gets installed.
+const OPENSSL_CA: &str = "--use-openssl-ca";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvSource {
+    /// Passed on from the desktop app's own environment.
+    Inherited,
+    Proxy,
+    /// `NODE_OPTIONS` and `NODE_EXTRA_CA_CERTS`.
+    Node,
+    /// `gateway.env` in the desktop settings.
+    Settings,
+    /// State dir, config path and port of the instance.
+    Instance,
+    Secret,
+}
+
+/// One variable of the gateway's environment.
+#[derive(Debug, Clone, Serialize)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+    pub source: EnvSource,
+}
+
+/// The gateway's environment, sorted by name.
+pub struct GatewayEnv(BTreeMap<String, (String, EnvSource)>);
+
+impl GatewayEnv {
+    fn set(&mut self, name: impl Into<String>, value: impl Into<String>, source: EnvSource) {
+        self.0.insert(name.into(), (value.into(), source));
+    }
+
+    fn remove(&mut self, name: &str) {
+        self.0.remove(name);
+    }
+
+    pub fn vars(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, (value, _))| (name.as_str(), value.as_str()))
+    }
+
+    /// The variables with secrets, sensitive-looking values and proxy
+    /// credentials replaced by `***`.
+    pub fn redacted(&self) -> Vec<EnvVar> {
+        self.0
+            .iter()
+            .map(|(name, (value, source))| {
+                let upper = name.to_ascii_uppercase();
+                let hidden = *source == EnvSource::Secret
+                    || SENSITIVE.iter().any(|word| upper.contains(word));
+                EnvVar {
+                    name: name.clone(),
+                    value: if hidden {
+                        "***".to_string()
+                    } else {
+                        proxy::redact(value)
+                    },
+                    source: *source,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Build the environment for running `inst`.
+pub fn build(inst: &Instance) -> GatewayEnv {
+    build_with(inst, std::env::vars())
+}
+
+/// `build` with `vars` standing in for the desktop app's environment.
+fn build_with(inst: &Instance, vars: impl IntoIterator<Item = (String, String)>) -> GatewayEnv {
+    let mut env = GatewayEnv(BTreeMap::new());
+
+    for (name, value) in vars {
+        let inherited = INHERITED.contains(&name.as_str())
+            || INHERITED_PREFIXES.iter().any(|p| name.starts_with(p));
+        if inherited && !name.starts_with(secrets::HANDLE_PREFIX) {
+            env.set(name, value, EnvSource::Inherited);
+        }
+    }
+
+    // For Node.js (undici/fetch) GLOBAL_AGENT_HTTP_PROXY is set as well,
+    // so libraries like global-agent can intercept requests
+    let proxy_vars = proxy::resolve_for(&inst.config_path).env_vars();
+    let proxied = !proxy_vars.is_empty();
+    for (name, value) in proxy_vars {
+        env.set(name, value, EnvSource::Proxy);
+    }
+
+    let mut node_options = std::env::var("NODE_OPTIONS").unwrap_or_default();
+    if proxied && !node_options.split_whitespace().any(|o| o == OPENSSL_CA) {
+        node_options = format!("{} {}", node_options, OPENSSL_CA)
+            .trim()
+            .to_string();
+    }
+    if !node_options.is_empty() {
+        env.set("NODE_OPTIONS", node_options, EnvSource::Node);
+    }
+
+    let gateway_settings = settings::load_settings().gateway;
+    let ca_certs = gateway_settings
+        .extra_ca_certs
+        .map(|p| p.to_string_lossy().into_owned())
+        .or_else(|| std::env::var("NODE_EXTRA_CA_CERTS").ok())
+        .filter(|p| !p.is_empty());
+    if let Some(path) = ca_certs {
+        env.set("NODE_EXTRA_CA_CERTS", path, EnvSource::Node);
+    }
+
+    for (name, value) in gateway_settings.env {
+        env.set(name, value, EnvSource::Settings);
+    }
+
+    // Every instance is told its own state dir and config under the
+    // gateway's names. The desktop's overrides were resolved into
+    // `inst.config_path` already and would only contradict it.
+    env.remove(config::CONFIG_ENV);
+    env.remove(config::PORT_ENV);
+    env.set(
+        STATE_DIR_ENV,
+        inst.state_dir.to_string_lossy(),
+        EnvSource::Instance,
+    );
+    env.set(
+        CONFIG_PATH_ENV,
+        inst.config_path.to_string_lossy(),
+        EnvSource::Instance,
+    );
+    if inst.is_default() {
+        // Hand --port and $OPENCLAW_GATEWAY_PORT on to the default gateway
+        if let Some((port, _)) = config::port_override() {
+            env.set(config::PORT_ENV, port.to_string(), EnvSource::Instance);
+        }
+    } else {
+        // Extra instances take the port from their own config
+        match config::load_config_from(&inst.config_path) {
+            Ok(cfg) => env.set(
+                config::PORT_ENV,
+                cfg.gateway.port.to_string(),
+                EnvSource::Instance,
+            ),
+            Err(e) => warn!(instance = %inst.id, error = %e, "cannot read instance port"),
+        }
+    }
+
+    // Secrets the config refers to, resolved only in the child's environment
+    for (handle, value) in secrets::gateway_env(&inst.config_path) {
+        env.set(handle, value, EnvSource::Secret);
+    }
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn extra_instance_gets_none_of_the_desktop_overrides() {
+        let dir = std::env::temp_dir().join(format!("openclaw-env-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_path = dir.join("openclaw.json");
+        std::fs::write(&config_path, r#"{ "gateway": { "port": 18800 } }"#).unwrap();
+        let inst = Instance {
+            id: "work".to_string(),
+            state_dir: dir.clone(),
+            config_path: config_path.clone(),
+            log_path: dir.join("desktop-gateway.log"),
+            autostart: false,
+        };
+
+        let desktop = [
+            ("PATH", "/usr/bin"),
+            ("OPENCLAW_CONFIG", "/home/u/desk/openclaw.json"),
+            ("OPENCLAW_CONFIG_PATH", "/home/u/desk/openclaw.json"),
+            ("OPENCLAW_STATE_DIR", "/home/u/desk"),
+            ("OPENCLAW_GATEWAY_PORT", "19000"),
+            ("OPENCLAW_LOG_LEVEL", "debug"),
+        ];
+        let env = build_with(
+            &inst,
+            desktop.map(|(name, value)| (name.to_string(), value.to_string())),
+        );
+        let get = |name: &str| {
+            env.vars()
+                .find(|(n, _)| *n == name)
+                .map(|(_, value)| value.to_string())
+        };
+
+        assert_eq!(get(config::CONFIG_ENV), None);
+        assert_eq!(get(CONFIG_PATH_ENV).map(PathBuf::from), Some(config_path));
+        assert_eq!(get(STATE_DIR_ENV).map(PathBuf::from), Some(dir.clone()));
+        assert_eq!(get(config::PORT_ENV).as_deref(), Some("18800"));
+        assert!(env.vars().all(|(_, value)| !value.contains("/home/u/desk")));
+        assert_eq!(get("PATH").as_deref(), Some("/usr/bin"));
+        assert_eq!(get("OPENCLAW_LOG_LEVEL").as_deref(), Some("debug"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,144 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::config;
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::logs;
+use crate::settings::{self, InstanceSettings};
+
+/// Key of the gateway configured in `~/.openclaw`, which the main window
+/// and tray connect to.
+pub const DEFAULT_INSTANCE: &str = "default";
+
+/// A local gateway and the files that belong to it. Each instance gets its
+/// own state dir, so config, port, token and log never overlap.
+#[derive(Debug, Clone, Serialize)]
+pub struct Instance {
+    pub id: String,
+    pub state_dir: PathBuf,
+    pub config_path: PathBuf,
+    pub log_path: PathBuf,
+    pub autostart: bool,
+}
+
+impl Instance {
+    pub fn is_default(&self) -> bool {
+        self.id == DEFAULT_INSTANCE
+    }
+
+    fn from_settings(s: &InstanceSettings) -> Self {
+        Self {
+            id: s.name.clone(),
+            state_dir: s.state_dir.clone(),
+            config_path: s.state_dir.join("openclaw.json"),
+            log_path: s.state_dir.join("desktop-gateway.log"),
+            autostart: s.autostart,
+        }
+    }
+}
+
+fn default_instance() -> AppResult<Instance> {
+    let config_path = config::config_path().ok_or_else(AppError::home_dir_unknown)?;
+    Ok(Instance {
+        id: DEFAULT_INSTANCE.to_string(),
+        state_dir: config_path.parent().map(PathBuf::from).unwrap_or_default(),
+        config_path,
+        log_path: logs::gateway_log_path(),
+        autostart: true,
+    })
+}
+
+pub fn get(id: &str) -> AppResult<Instance> {
+    if id == DEFAULT_INSTANCE {
+        return default_instance();
+    }
+    settings::load_settings()
+        .instances
+        .iter()
+        .find(|s| s.name == id)
+        .map(Instance::from_settings)
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorKind::InvalidInput,
+                format!("No gateway instance named \"{}\"", id),
+            )
+        })
+}
+
+/// The default instance followed by the configured extra ones.
+pub fn all() -> Vec<Instance> {
+    default_instance()
+        .into_iter()
+        .chain(
+            settings::load_settings()
+                .instances
+                .iter()
+                .map(Instance::from_settings),
+        )
+        .collect()
+}
+
+/// Ports in the configs of all instances, whether they run or not.
+pub fn assigned_ports() -> Vec<u16> {
+    all()
+        .iter()
+        .filter_map(|inst| config::load_config_from(&inst.config_path).ok())
+        .map(|c| c.gateway.port)
+        .collect()
+}
+
+/// Register a new instance, creating its state dir and config. Without an
+/// explicit `state_dir` it lives in `~/.openclaw-<name>`.
+pub fn add(name: &str, state_dir: Option<PathBuf>, port: u16) -> AppResult<Instance> {
+    let invalid = |msg: String| AppError::new(ErrorKind::InvalidInput, msg);
+
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        return Err(invalid(format!(
+            "Instance name may only contain letters, digits, '-' and '_', got \"{}\"",
+            name
+        )));
+    }
+
+    let mut s = settings::load_settings();
+    if name == DEFAULT_INSTANCE || s.instances.iter().any(|i| i.name == name) {
+        return Err(invalid(format!(
+            "A gateway instance named \"{}\" already exists",
+            name
+        )));
+    }
+
+    let state_dir = match state_dir {
+        Some(dir) => dir,
+        None => dirs::home_dir()
+            .ok_or_else(AppError::home_dir_unknown)?
+            .join(format!(".openclaw-{}", name)),
+    };
+    let entry = InstanceSettings {
+        name: name.to_string(),
+        state_dir,
+        autostart: false,
+    };
+    let instance = Instance::from_settings(&entry);
+
+    config::create_instance_config(&instance.config_path, port)?;
+    s.instances.push(entry);
+    settings::save_settings(&s)?;
+    Ok(instance)
+}
+
+/// Forget an instance. Its state dir is left on disk.
+pub fn remove(id: &str) -> AppResult<()> {
+    if id == DEFAULT_INSTANCE {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "The default gateway instance cannot be removed",
+        ));
+    }
+    let mut s = settings::load_settings();
+    s.instances.retain(|i| i.name != id);
+    settings::save_settings(&s)
+}
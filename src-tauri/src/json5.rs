@@ -0,0 +1,716 @@
+//! The relaxed JSON syntax (JSON5) the OpenClaw CLI accepts in
+//! openclaw.json: comments, trailing commas, unquoted keys, single-quoted
+//! strings and a few extra number forms.
+//!
+//! Besides reading, `edit` applies changes to such a file in place, so
+//! comments and formatting outside the changed values survive a write.
+
+use serde_json::{Map, Number, Value};
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct Error {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Byte range of a value in the source text.
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub start: usize,
+    pub end: usize,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    Object(Vec<Member>),
+    /// Arrays and scalars, which are only ever replaced as a whole.
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub key: String,
+    pub key_start: usize,
+    pub value: Node,
+    /// Offset of the comma following the value, if there is one.
+    pub comma: Option<usize>,
+}
+
+pub fn parse(text: &str) -> Result<Value, Error> {
+    parse_spanned(text).map(|(value, _)| value)
+}
+
+/// Parse `text`, also returning where each value sits in it.
+pub fn parse_spanned(text: &str) -> Result<(Value, Node), Error> {
+    let mut p = Parser {
+        text,
+        bytes: text.as_bytes(),
+        pos: 0,
+    };
+    if text.starts_with('\u{feff}') {
+        p.pos = '\u{feff}'.len_utf8();
+    }
+
+    let parsed = p.value()?;
+    p.skip_trivia()?;
+    if p.pos < p.bytes.len() {
+        return Err(p.error("trailing characters"));
+    }
+    Ok(parsed)
+}
+
+/// 1-based line and column of byte `offset`, counted like serde_json does.
+pub fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text.as_bytes()[..offset.min(text.len())];
+    let line = before.iter().filter(|b| **b == b'\n').count() + 1;
+    let line_start = before
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |i| i + 1);
+    (line, before.len() - line_start + 1)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> Error {
+        let (line, column) = line_col(self.text, self.pos);
+        Error {
+            message: message.to_string(),
+            line,
+            column,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.text.get(self.pos..)?.chars().next()
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_trivia(&mut self) -> Result<(), Error> {
+        loop {
+            match self.peek() {
+                Some(b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c) => self.pos += 1,
+                Some(b'/') if self.bytes.get(self.pos + 1) == Some(&b'/') => {
+                    self.pos = self.text[self.pos..]
+                        .find('\n')
+                        .map_or(self.bytes.len(), |i| self.pos + i);
+                }
+                Some(b'/') if self.bytes.get(self.pos + 1) == Some(&b'*') => {
+                    match self.text[self.pos + 2..].find("*/") {
+                        Some(i) => self.pos += i + 4,
+                        None => return Err(self.error("unterminated comment")),
+                    }
+                }
+                Some(_) if self.peek_char().is_some_and(|c| c.is_whitespace()) => {
+                    self.pos += self.peek_char().map_or(1, char::len_utf8);
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<(Value, Node), Error> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        let (value, kind) = match self.peek() {
+            Some(b'{') => self.object()?,
+            Some(b'[') => (self.array()?, NodeKind::Other),
+            Some(b'"' | b'\'') => (Value::String(self.string()?), NodeKind::Other),
+            Some(b't') => (self.literal("true", Value::Bool(true))?, NodeKind::Other),
+            Some(b'f') => (self.literal("false", Value::Bool(false))?, NodeKind::Other),
+            Some(b'n') => (self.literal("null", Value::Null)?, NodeKind::Other),
+            Some(b'-' | b'+' | b'.' | b'0'..=b'9' | b'I' | b'N') => {
+                (self.number()?, NodeKind::Other)
+            }
+            Some(_) => return Err(self.error("expected value")),
+            None => return Err(self.error("EOF while parsing a value")),
+        };
+        Ok((
+            value,
+            Node {
+                start,
+                end: self.pos,
+                kind,
+            },
+        ))
+    }
+
+    fn object(&mut self) -> Result<(Value, NodeKind), Error> {
+        self.pos += 1;
+        let mut map = Map::new();
+        let mut members = Vec::new();
+
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok((Value::Object(map), NodeKind::Object(members)));
+                }
+                None => return Err(self.error("EOF while parsing an object")),
+                _ => {}
+            }
+
+            let key_start = self.pos;
+            let key = match self.peek() {
+                Some(b'"' | b'\'') => self.string()?,
+                _ => self.identifier()?,
+            };
+
+            self.skip_trivia()?;
+            if self.peek() != Some(b':') {
+                return Err(self.error("expected `:`"));
+            }
+            self.pos += 1;
+
+            let (value, node) = self.value()?;
+            self.skip_trivia()?;
+            let comma = (self.peek() == Some(b',')).then_some(self.pos);
+            match comma {
+                Some(_) => self.pos += 1,
+                None if self.peek() != Some(b'}') => {
+                    return Err(self.error("expected `,` or `}`"));
+                }
+                None => {}
+            }
+
+            map.insert(key.clone(), value);
+            members.push(Member {
+                key,
+                key_start,
+                value: node,
+                comma,
+            });
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, Error> {
+        self.pos += 1;
+        let mut items = Vec::new();
+
+        loop {
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                None => return Err(self.error("EOF while parsing a list")),
+                _ => {}
+            }
+
+            items.push(self.value()?.0);
+            self.skip_trivia()?;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {}
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    /// Unquoted property names: ECMAScript identifiers, without escapes.
+    fn identifier(&mut self) -> Result<String, Error> {
+        let start = self.pos;
+        while let Some(c) = self.peek_char() {
+            let valid = c == '_'
+                || c == '$'
+                || c.is_alphabetic()
+                || (self.pos > start && c.is_alphanumeric());
+            if !valid {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+        if self.pos == start {
+            return Err(self.error("expected property name"));
+        }
+        Ok(self.text[start..self.pos].to_string())
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let quote = self.bytes[self.pos];
+        self.pos += 1;
+        let mut out = String::new();
+
+        loop {
+            let Some(c) = self.peek_char() else {
+                return Err(self.error("EOF while parsing a string"));
+            };
+            self.pos += c.len_utf8();
+            match c {
+                c if c as u32 == quote as u32 => return Ok(out),
+                '\\' => self.escape(&mut out)?,
+                '\n' | '\r' => {
+                    self.pos -= 1;
+                    return Err(self.error("control character in string"));
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self, out: &mut String) -> Result<(), Error> {
+        let Some(c) = self.peek_char() else {
+            return Err(self.error("EOF while parsing a string"));
+        };
+        self.pos += c.len_utf8();
+        match c {
+            'b' => out.push('\u{8}'),
+            'f' => out.push('\u{c}'),
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            'v' => out.push('\u{b}'),
+            '0' => out.push('\0'),
+            // Line continuation
+            '\n' | '\u{2028}' | '\u{2029}' => {}
+            '\r' => {
+                if self.peek() == Some(b'\n') {
+                    self.pos += 1;
+                }
+            }
+            'x' => {
+                let code = self.hex(2)?;
+                out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+            }
+            'u' => {
+                let mut code = self.hex(4)?;
+                // Surrogate pair
+                if (0xd800..0xdc00).contains(&code) && self.text[self.pos..].starts_with("\\u") {
+                    self.pos += 2;
+                    let low = self.hex(4)?;
+                    code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                }
+                out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+            }
+            c => out.push(c),
+        }
+        Ok(())
+    }
+
+    fn hex(&mut self, digits: usize) -> Result<u32, Error> {
+        let code = self
+            .text
+            .get(self.pos..self.pos + digits)
+            .and_then(|s| u32::from_str_radix(s, 16).ok())
+            .ok_or_else(|| self.error("invalid escape"))?;
+        self.pos += digits;
+        Ok(code)
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, Error> {
+        if !self.text[self.pos..].starts_with(word) {
+            return Err(self.error("expected value"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Value, Error> {
+        let start = self.pos;
+        let negative = self.peek() == Some(b'-');
+        if matches!(self.peek(), Some(b'-' | b'+')) {
+            self.pos += 1;
+        }
+
+        let rest = &self.text[self.pos..];
+        if rest.starts_with("Infinity") || rest.starts_with("NaN") {
+            return Err(self.error("Infinity and NaN cannot be represented"));
+        }
+
+        if rest.starts_with("0x") || rest.starts_with("0X") {
+            self.pos += 2;
+            let digits = self.pos;
+            while self.peek().is_some_and(|b| b.is_ascii_hexdigit()) {
+                self.pos += 1;
+            }
+            let n = i64::from_str_radix(&self.text[digits..self.pos], 16)
+                .map_err(|_| self.error("invalid number"))?;
+            return Ok(Value::from(if negative { -n } else { n }));
+        }
+
+        while let Some(b) = self.peek() {
+            let exponent_sign =
+                matches!(b, b'+' | b'-') && matches!(self.bytes[self.pos - 1], b'e' | b'E');
+            if !(b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E') || exponent_sign) {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        // Normalize JSON5's `+1`, `.5` and `5.` into something Rust parses
+        let raw = self.text[start..self.pos].trim_start_matches('+');
+        let (sign, digits) = match raw.strip_prefix('-') {
+            Some(d) => ("-", d),
+            None => ("", raw),
+        };
+        let mut normalized = format!("{}{}", sign, digits);
+        if digits.starts_with('.') {
+            normalized = format!("{}0{}", sign, digits);
+        }
+        let normalized = normalized.replace(".e", ".0e").replace(".E", ".0E");
+        let normalized = match normalized.strip_suffix('.') {
+            Some(n) => format!("{}.0", n),
+            None => normalized,
+        };
+
+        let number = if normalized.contains(['.', 'e', 'E']) {
+            normalized.parse::<f64>().ok().and_then(Number::from_f64)
+        } else if let Ok(n) = normalized.parse::<i64>() {
+            Some(Number::from(n))
+        } else {
+            normalized.parse::<u64>().ok().map(Number::from)
+        };
+        number
+            .map(Value::Number)
+            .ok_or_else(|| self.error("invalid number"))
+    }
+}
+
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// Rewrite `text` so that it parses to `new`, replacing only the values
+/// that changed and appending new keys after the existing ones.
+/// Returns `None` if `text` doesn't parse or the result can't be verified.
+pub fn edit(text: &str, new: &Value) -> Option<String> {
+    let (old, root) = parse_spanned(text).ok()?;
+    let mut edits = Vec::new();
+    edit_node(text, &root, &old, new, &mut edits);
+
+    edits.sort_by_key(|e| e.start);
+    if edits.windows(2).any(|w| w[1].start < w[0].end) {
+        return None;
+    }
+    let mut out = text.to_string();
+    for e in edits.iter().rev() {
+        out.replace_range(e.start..e.end, &e.text);
+    }
+
+    // Never hand back something that doesn't say what was asked
+    (parse(&out).ok()? == *new).then_some(out)
+}
+
+fn edit_node(text: &str, node: &Node, old: &Value, new: &Value, edits: &mut Vec<Edit>) {
+    if old == new {
+        return;
+    }
+    match (&node.kind, old, new) {
+        (NodeKind::Object(members), Value::Object(old_map), Value::Object(new_map))
+            if !members.is_empty() =>
+        {
+            edit_object(text, node, members, old_map, new_map, edits)
+        }
+        _ => edits.push(Edit {
+            start: node.start,
+            end: node.end,
+            text: render(new, line_indent(text, node.start), true),
+        }),
+    }
+}
+
+fn edit_object(
+    text: &str,
+    node: &Node,
+    members: &[Member],
+    old_map: &Map<String, Value>,
+    new_map: &Map<String, Value>,
+    edits: &mut Vec<Edit>,
+) {
+    let removed: Vec<usize> = (0..members.len())
+        .filter(|i| !new_map.contains_key(&members[*i].key))
+        .collect();
+    let added: Vec<(&String, &Value)> = new_map
+        .iter()
+        .filter(|(k, _)| !old_map.contains_key(*k))
+        .collect();
+
+    // Removals next to each other or next to an insertion would need
+    // overlapping edits; rewrite the whole object instead
+    let last = members.len() - 1;
+    if (!removed.is_empty() && !added.is_empty()) || (removed.contains(&last) && removed.len() > 1)
+    {
+        edits.push(Edit {
+            start: node.start,
+            end: node.end,
+            text: render(
+                &Value::Object(new_map.clone()),
+                line_indent(text, node.start),
+                true,
+            ),
+        });
+        return;
+    }
+
+    for (i, member) in members.iter().enumerate() {
+        match (old_map.get(&member.key), new_map.get(&member.key)) {
+            (Some(old), Some(new)) => edit_node(text, &member.value, old, new, edits),
+            _ => edits.push(remove_member(text, members, i)),
+        }
+    }
+
+    if added.is_empty() {
+        return;
+    }
+
+    let first = &members[0];
+    let multiline = text[node.start..first.key_start].contains('\n');
+    let indent = line_indent(text, first.key_start);
+    let rendered: Vec<String> = added
+        .iter()
+        .map(|(k, v)| {
+            let key = serde_json::to_string(k).unwrap_or_default();
+            format!("{}: {}", key, render(v, indent, multiline))
+        })
+        .collect();
+    let separator = if multiline {
+        format!(",\n{}", indent)
+    } else {
+        ", ".to_string()
+    };
+
+    let tail = &members[last];
+    let edit = match tail.comma {
+        // Keep the file's trailing-comma style
+        Some(comma) if multiline => {
+            let at = after_line_comment(text, comma + 1);
+            Edit {
+                start: at,
+                end: at,
+                text: format!("\n{}{},", indent, rendered.join(&separator)),
+            }
+        }
+        Some(comma) => Edit {
+            start: comma + 1,
+            end: comma + 1,
+            text: format!(" {},", rendered.join(&separator)),
+        },
+        None if multiline => {
+            edits.push(Edit {
+                start: tail.value.end,
+                end: tail.value.end,
+                text: ",".to_string(),
+            });
+            let at = after_line_comment(text, tail.value.end);
+            Edit {
+                start: at,
+                end: at,
+                text: format!("\n{}{}", indent, rendered.join(&separator)),
+            }
+        }
+        None => Edit {
+            start: tail.value.end,
+            end: tail.value.end,
+            text: format!("{}{}", separator, rendered.join(&separator)),
+        },
+    };
+    edits.push(edit);
+}
+
+/// End of the line at `pos` if only a `//` comment follows there, else
+/// `pos`, so new members don't push a comment off the line it annotates.
+fn after_line_comment(text: &str, pos: usize) -> usize {
+    let line_end = text[pos..].find('\n').map_or(text.len(), |p| pos + p);
+    if text[pos..line_end].trim_start().starts_with("//") {
+        line_end
+    } else {
+        pos
+    }
+}
+
+fn remove_member(text: &str, members: &[Member], i: usize) -> Edit {
+    let member = &members[i];
+    let (mut start, mut end) = (member.key_start, member.value.end);
+    match member.comma {
+        Some(comma) => end = comma + 1,
+        // The last member takes the comma before it along
+        None if i > 0 => {
+            if let Some(comma) = members[i - 1].comma {
+                start = comma;
+            }
+        }
+        None => {}
+    }
+
+    let horizontal = [' ', '\t'];
+    end = text.len() - text[end..].trim_start_matches(horizontal).len();
+
+    // Drop the whole line when the member had it to itself
+    let line_start = text[..start].rfind('\n').map_or(0, |p| p + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |p| end + p + 1);
+    if text[line_start..start].trim().is_empty() && text[end..line_end].trim().is_empty() {
+        start = line_start;
+        end = line_end;
+    } else if text[end..line_end].trim().is_empty() {
+        // Nothing follows on this line, so don't leave a trailing space
+        start = text[..start].trim_end_matches(horizontal).len();
+    }
+
+    Edit {
+        start,
+        end,
+        text: String::new(),
+    }
+}
+
+/// Leading whitespace of the line containing `pos`.
+fn line_indent(text: &str, pos: usize) -> &str {
+    let line_start = text[..pos].rfind('\n').map_or(0, |p| p + 1);
+    let line = &text[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Serialize `value` for insertion at a line indented by `indent`.
+fn render(value: &Value, indent: &str, multiline: bool) -> String {
+    if !multiline {
+        return serde_json::to_string(value).unwrap_or_default();
+    }
+    serde_json::to_string_pretty(value)
+        .unwrap_or_default()
+        .replace('\n', &format!("\n{}", indent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// `edit` `text` with `f` applied to its parsed value.
+    fn edited(text: &str, f: impl FnOnce(&mut Value)) -> Option<String> {
+        let mut value = parse(text).unwrap();
+        f(&mut value);
+        edit(text, &value)
+    }
+
+    #[test]
+    fn edit_keeps_comments() {
+        let text = "// OpenClaw\n{\n  /* the gateway */\n  port: 18789, // default\n  bind: 'loopback',\n}\n";
+        let out = edited(text, |v| v["port"] = json!(18790)).unwrap();
+        assert_eq!(
+            out,
+            "// OpenClaw\n{\n  /* the gateway */\n  port: 18790, // default\n  bind: 'loopback',\n}\n"
+        );
+    }
+
+    #[test]
+    fn parses_trailing_commas() {
+        assert_eq!(
+            parse("{a: [1, 2,], b: {c: 3,},}").unwrap(),
+            json!({"a": [1, 2], "b": {"c": 3}})
+        );
+    }
+
+    #[test]
+    fn parses_unquoted_and_single_quoted_keys() {
+        let value = parse("{plain: 1, $dollar_1: 2, 'single': 3, \"double\": 4}").unwrap();
+        assert_eq!(
+            value,
+            json!({"plain": 1, "$dollar_1": 2, "single": 3, "double": 4})
+        );
+    }
+
+    #[test]
+    fn parses_extra_number_forms() {
+        assert_eq!(
+            parse("[0x1F, -0xa, .5, +1, 5., +.25]").unwrap(),
+            json!([31, -10, 0.5, 1, 5.0, 0.25])
+        );
+    }
+
+    #[test]
+    fn removes_first_middle_and_last_member() {
+        let text = "{\n  a: 1,\n  b: 2,\n  c: 3\n}";
+        let remove = |key: &str| {
+            edited(text, |v| {
+                v.as_object_mut().unwrap().remove(key);
+            })
+        };
+        assert_eq!(remove("a").unwrap(), "{\n  b: 2,\n  c: 3\n}");
+        assert_eq!(remove("b").unwrap(), "{\n  a: 1,\n  c: 3\n}");
+        assert_eq!(remove("c").unwrap(), "{\n  a: 1,\n  b: 2\n}");
+
+        let inline = "{a: 1, b: 2, c: 3}";
+        let out = edited(inline, |v| {
+            v.as_object_mut().unwrap().remove("c");
+        });
+        assert_eq!(out.unwrap(), "{a: 1, b: 2}");
+    }
+
+    #[test]
+    fn adds_key_to_empty_object() {
+        let out = edited("{\n  gateway: {}\n}", |v| v["gateway"]["port"] = json!(1)).unwrap();
+        assert_eq!(parse(&out).unwrap(), json!({"gateway": {"port": 1}}));
+        assert!(out.starts_with("{\n  gateway: {"));
+    }
+
+    #[test]
+    fn adds_key_to_nested_object() {
+        let text = "{\n  gateway: {\n    port: 1, // keep\n  },\n}";
+        let out = edited(text, |v| v["gateway"]["bind"] = json!("lan")).unwrap();
+        assert_eq!(
+            out,
+            "{\n  gateway: {\n    port: 1, // keep\n    \"bind\": \"lan\",\n  },\n}"
+        );
+    }
+
+    #[test]
+    fn adds_key_after_commented_last_member() {
+        let text = "{\n  port: 1 // keep\n}";
+        let out = edited(text, |v| v["bind"] = json!("lan")).unwrap();
+        assert_eq!(out, "{\n  port: 1, // keep\n  \"bind\": \"lan\"\n}");
+    }
+
+    #[test]
+    fn rewrites_object_when_removing_and_adding() {
+        let out = edited("{a: 1, b: 2}", |v| *v = json!({"a": 1, "c": 3})).unwrap();
+        assert_eq!(parse(&out).unwrap(), json!({"a": 1, "c": 3}));
+    }
+
+    #[test]
+    fn overlapping_edits_give_up() {
+        // Removing `a` runs up to `b`, removing `b` reaches back to `a`'s comma
+        let text = "{\n  a: 1, b: 2,\n  c: 3\n}";
+        assert_eq!(edited(text, |v| *v = json!({"c": 3})), None);
+        assert_eq!(edit("{a: ", &json!({})), None);
+    }
+
+    #[test]
+    fn line_col_counts_bytes_after_multibyte_text() {
+        let text = "{\n  name: \"网关\", x";
+        let offset = text.find('x').unwrap();
+        // Columns count bytes like serde_json: 网 and 关 are three each
+        assert_eq!(line_col(text, offset), (2, 19));
+        assert_eq!(line_col(text, text.len() + 10), (2, 20));
+
+        let err = parse("{\n  \"名\": }").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 10));
+    }
+}
@@ -1,21 +1,83 @@
+mod backups;
 mod commands;
 mod config;
+mod connection;
+mod doctor;
+mod error;
 mod gateway;
+mod gateway_env;
+mod instance;
+mod json5;
+mod logging;
+mod logs;
+mod pac;
+mod profiles;
+mod proxy;
+mod proxy_check;
+mod secrets;
+mod settings;
 mod setup;
 mod tray;
+mod watcher;
 
 use tauri::Manager;
 
-pub fn run() {
+pub use config::CliOverrides;
+
+pub fn run(overrides: CliOverrides) {
+    config::set_cli_overrides(overrides);
+    logging::init();
+    if let Some(sources) = config::config_sources() {
+        tracing::info!(
+            config = %sources.config_path.display(),
+            config_source = ?sources.config_path_source,
+            port_source = ?sources.port,
+            "resolved openclaw config"
+        );
+    }
+
     let first_run = setup::is_first_run();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .invoke_handler(tauri::generate_handler![
             commands::get_gateway_info,
+            commands::read_config,
+            commands::update_config,
+            commands::get_gateway_config,
+            commands::update_gateway_settings,
             commands::check_gateway_status,
             commands::get_gateway_url,
             commands::start_gateway,
+            commands::get_gateway_state,
+            commands::check_port_conflict,
+            commands::resolve_port_conflict,
+            commands::list_gateway_instances,
+            commands::add_gateway_instance,
+            commands::remove_gateway_instance,
+            commands::start_gateway_instance,
+            commands::stop_gateway_instance,
+            commands::tail_gateway_log,
+            commands::search_gateway_log,
+            commands::open_log_window,
+            commands::check_config,
+            commands::apply_config_fixes,
+            commands::list_config_backups,
+            commands::preview_config_backup,
+            commands::restore_config_backup,
+            commands::get_connection_settings,
+            commands::set_connection,
+            commands::get_log_level,
+            commands::set_log_level,
+            commands::list_profiles,
+            commands::save_profile,
+            commands::delete_profile,
+            commands::switch_profile,
+            commands::get_secret_backend,
+            commands::secure_config_secrets,
+            commands::proxy_for_url,
+            commands::test_proxy,
+            commands::get_gateway_env,
             setup::is_first_run,
             setup::check_prerequisites,
             setup::install_openclaw,
@@ -23,6 +85,16 @@ pub fn run() {
             setup::save_initial_config,
         ])
         .setup(move |app| {
+            gateway::init(app.handle());
+            logs::start_rotation_monitor();
+            watcher::start(app.handle().clone());
+
+            if let Some(win) = app.get_webview_window("main") {
+                if let Ok(url) = win.url() {
+                    profiles::set_home_url(url);
+                }
+            }
+
             if first_run {
                 // First run: load setup wizard, don't start gateway
                 let win = app
@@ -33,10 +105,11 @@ pub fn run() {
                 // Normal run: start gateway
                 gateway::ensure_started();
             }
+            gateway::start_autostart_instances();
 
             // Create system tray (includes health monitor)
             if let Err(e) = tray::create_tray(app.handle()) {
-                eprintln!("Failed to create tray: {}", e);
+                tracing::error!(error = %e, "failed to create tray");
             }
 
             // Intercept window close → hide instead of quit
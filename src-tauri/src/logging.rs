@@ -0,0 +1,141 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::{self, FormatEvent, FormatFields};
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{reload, Registry};
+
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::settings;
+
+/// Daily files kept in the log directory.
+const MAX_LOG_FILES: usize = 7;
+
+static FILTER: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+static GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// `$XDG_STATE_HOME/openclaw-desktop`, where the app's own logs live.
+pub fn log_dir() -> PathBuf {
+    dirs::state_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".local/state")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("openclaw-desktop")
+}
+
+/// Install the global subscriber: a daily-rotated file in `log_dir()` plus an
+/// optional stderr mirror. Must run before anything else logs.
+pub fn init() {
+    let cfg = settings::load_settings().logging;
+    let level = cfg.level.parse().unwrap_or(LevelFilter::INFO);
+    let (filter, handle) = reload::Layer::new(level);
+
+    let file_layer = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("desktop")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir())
+        .map(|appender| {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let _ = GUARD.set(guard);
+            tracing_subscriber::fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+        });
+
+    // journald adds its own timestamps and reads priority from a `<N>` prefix
+    let journald = std::env::var_os("JOURNAL_STREAM").is_some();
+    let stderr_layer = cfg.stderr.then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .with_ansi(false)
+            .event_format(SyslogPrefix {
+                enabled: journald,
+                inner: format::Format::default().without_time(),
+            })
+    });
+
+    let (file_layer, file_error) = match file_layer {
+        Ok(layer) => (Some(layer), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    let result = tracing_subscriber::registry()
+        .with(filter)
+        .with(file_layer)
+        .with(stderr_layer)
+        .try_init();
+
+    if result.is_ok() {
+        let _ = FILTER.set(handle);
+    }
+    if let Some(e) = file_error {
+        tracing::warn!(dir = %log_dir().display(), error = %e, "failed to open log file");
+    }
+}
+
+pub fn current_level() -> String {
+    FILTER
+        .get()
+        .and_then(|h| h.with_current(|f| f.to_string()).ok())
+        .unwrap_or_else(|| settings::load_settings().logging.level)
+        .to_lowercase()
+}
+
+/// Change the log level of the running app and remember it for next launch.
+pub fn set_level(level: &str) -> AppResult<()> {
+    let filter: LevelFilter = level
+        .parse()
+        .map_err(|_| AppError::new(ErrorKind::InvalidInput, format!("Unknown log level: {}", level)))?;
+
+    if let Some(handle) = FILTER.get() {
+        handle
+            .reload(filter)
+            .map_err(|e| AppError::internal("Failed to change log level").with_source(e))?;
+    }
+
+    let mut s = settings::load_settings();
+    s.logging.level = filter.to_string().to_lowercase();
+    settings::save_settings(&s)?;
+
+    tracing::info!(%filter, "log level changed");
+    Ok(())
+}
+
+/// Prefixes each line with its sd-daemon(3) priority when `enabled`.
+struct SyslogPrefix<F> {
+    enabled: bool,
+    inner: F,
+}
+
+impl<S, N, F> FormatEvent<S, N> for SyslogPrefix<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: format::Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        if self.enabled {
+            let priority = match *event.metadata().level() {
+                Level::ERROR => 3,
+                Level::WARN => 4,
+                Level::INFO => 6,
+                _ => 7,
+            };
+            write!(writer, "<{}>", priority)?;
+        }
+        self.inner.format_event(ctx, writer, event)
+    }
+}
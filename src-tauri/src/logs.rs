@@ -0,0 +1,518 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+use tracing::{info, warn};
+
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::instance;
+use crate::settings::{self, LogSettings};
+
+/// How much of the end of the file `tail` reads before splitting into lines.
+const TAIL_WINDOW_BYTES: u64 = 2 * 1024 * 1024;
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+const ROTATION_INTERVAL: Duration = Duration::from_secs(60);
+
+static FOLLOWING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogLine {
+    pub timestamp: Option<String>,
+    pub level: Option<LogLevel>,
+    pub message: String,
+}
+
+/// New lines of one instance's log, emitted as `gateway-log`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogBatch {
+    pub instance: String,
+    pub lines: Vec<LogLine>,
+}
+
+/// Where the default gateway's stdout and stderr are captured.
+pub fn gateway_log_path() -> PathBuf {
+    dirs::home_dir()
+        .map(|h| h.join(".openclaw/desktop-gateway.log"))
+        .unwrap_or_else(|| PathBuf::from("/tmp/openclaw-gateway.log"))
+}
+
+impl LogLevel {
+    fn parse(word: &str) -> Option<Self> {
+        match word.to_ascii_lowercase().as_str() {
+            "trace" | "verbose" => Some(Self::Trace),
+            "debug" => Some(Self::Debug),
+            "info" => Some(Self::Info),
+            "warn" | "warning" => Some(Self::Warn),
+            "error" | "err" => Some(Self::Error),
+            "fatal" | "critical" => Some(Self::Fatal),
+            _ => None,
+        }
+    }
+
+    /// pino-style numeric levels (10 = trace ... 60 = fatal).
+    fn from_number(n: u64) -> Option<Self> {
+        match n {
+            10 => Some(Self::Trace),
+            20 => Some(Self::Debug),
+            30 => Some(Self::Info),
+            40 => Some(Self::Warn),
+            50 => Some(Self::Error),
+            60 => Some(Self::Fatal),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a single line, recognising JSON log records and plain text lines
+/// that start with a timestamp and/or a level keyword.
+pub fn parse_line(raw: &str) -> LogLine {
+    let raw = raw.trim_end();
+
+    if raw.starts_with('{') {
+        if let Ok(serde_json::Value::Object(obj)) = serde_json::from_str(raw) {
+            let level = obj.get("level").and_then(|l| match l {
+                serde_json::Value::Number(n) => n.as_u64().and_then(LogLevel::from_number),
+                serde_json::Value::String(s) => LogLevel::parse(s),
+                _ => None,
+            });
+            let timestamp = obj
+                .get("time")
+                .or_else(|| obj.get("timestamp"))
+                .map(|t| match t {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                });
+            let message = obj
+                .get("msg")
+                .or_else(|| obj.get("message"))
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| raw.to_string());
+            return LogLine {
+                timestamp,
+                level,
+                message,
+            };
+        }
+    }
+
+    // Detect on the trimmed line, but keep indentation of unrecognised lines
+    // (stack traces) intact.
+    let mut rest = raw.trim_start();
+    let mut timestamp = None;
+    if let Some(first) = rest.split_whitespace().next() {
+        let candidate = first.trim_matches(|c| c == '[' || c == ']');
+        if looks_like_timestamp(candidate) {
+            timestamp = Some(candidate.to_string());
+            rest = rest[first.len()..].trim_start();
+        }
+    }
+
+    let mut level = None;
+    if let Some(word) = rest.split_whitespace().next() {
+        level = LogLevel::parse(word.trim_matches(|c: char| !c.is_ascii_alphabetic()));
+        if level.is_some() {
+            rest = rest[word.len()..].trim_start();
+        }
+    }
+
+    let message = if timestamp.is_none() && level.is_none() {
+        raw.to_string()
+    } else {
+        rest.to_string()
+    };
+
+    LogLine {
+        timestamp,
+        level,
+        message,
+    }
+}
+
+/// ISO-8601 dates ("2025-01-31T...") or clock times ("12:34:56").
+fn looks_like_timestamp(word: &str) -> bool {
+    let b = word.as_bytes();
+    let is_date =
+        b.len() >= 10 && b[..4].iter().all(u8::is_ascii_digit) && b[4] == b'-' && b[7] == b'-';
+    let is_time = b.len() >= 8 && b[2] == b':' && b[5] == b':' && b[0].is_ascii_digit();
+    is_date || is_time
+}
+
+fn passes(line: &LogLine, min_level: Option<LogLevel>) -> bool {
+    match (min_level, line.level) {
+        (None, _) => true,
+        // Continuation lines (stack traces etc.) have no level; keep them visible
+        (Some(_), None) => true,
+        (Some(min), Some(level)) => level >= min,
+    }
+}
+
+/// Last `lines` lines of the gateway log at `path` at or above `min_level`.
+pub fn tail(path: &Path, lines: usize, min_level: Option<LogLevel>) -> AppResult<Vec<LogLine>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let read_error = |e| log_read_error(path, e);
+    let mut file = File::open(path).map_err(read_error)?;
+    let len = file.metadata().map_err(read_error)?.len();
+    let start = len.saturating_sub(TAIL_WINDOW_BYTES);
+    file.seek(SeekFrom::Start(start)).map_err(read_error)?;
+
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).map_err(read_error)?;
+    let text = String::from_utf8_lossy(&buf);
+
+    let mut iter = text.lines();
+    if start > 0 {
+        // We most likely landed mid-line
+        iter.next();
+    }
+
+    let mut out: VecDeque<LogLine> = VecDeque::with_capacity(lines);
+    for line in iter.map(parse_line).filter(|l| passes(l, min_level)) {
+        if out.len() == lines {
+            out.pop_front();
+        }
+        out.push_back(line);
+    }
+    Ok(out.into())
+}
+
+fn log_read_error(path: &Path, e: io::Error) -> AppError {
+    AppError::new(ErrorKind::LogRead, format!("Failed to read {}", path.display()))
+        .with_path(path)
+        .with_source(e)
+}
+
+/// Case-insensitive substring search over the whole log, newest `limit` matches.
+pub fn search(
+    path: &Path,
+    query: &str,
+    min_level: Option<LogLevel>,
+    limit: usize,
+) -> AppResult<Vec<LogLine>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(path).map_err(|e| log_read_error(path, e))?;
+    let needle = query.to_lowercase();
+
+    let mut out: VecDeque<LogLine> = VecDeque::new();
+    for raw in BufReader::new(file).split(b'\n').map_while(Result::ok) {
+        let raw = String::from_utf8_lossy(&raw);
+        if !raw.to_lowercase().contains(&needle) {
+            continue;
+        }
+        let line = parse_line(&raw);
+        if !passes(&line, min_level) {
+            continue;
+        }
+        if out.len() == limit {
+            out.pop_front();
+        }
+        out.push_back(line);
+    }
+    Ok(out.into())
+}
+
+/// Follow every instance's log file and emit new lines as `gateway-log`
+/// batches. Only one follower runs; it handles truncation by starting over.
+pub fn start_follower(app: AppHandle) {
+    if FOLLOWING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        // Read offset and trailing partial line of each followed file
+        let mut cursors: HashMap<PathBuf, (u64, String)> = HashMap::new();
+
+        loop {
+            std::thread::sleep(FOLLOW_INTERVAL);
+
+            for inst in instance::all() {
+                let (pos, partial) = cursors.entry(inst.log_path.clone()).or_insert_with(|| {
+                    let len = fs::metadata(&inst.log_path).map(|m| m.len()).unwrap_or(0);
+                    (len, String::new())
+                });
+                let Some(lines) = read_new_lines(&inst.log_path, pos, partial) else {
+                    continue;
+                };
+
+                let batch = LogBatch {
+                    instance: inst.id,
+                    lines,
+                };
+                if let Err(e) = app.emit_to("logs", "gateway-log", batch) {
+                    warn!(error = %e, "failed to emit gateway log lines");
+                }
+            }
+        }
+    });
+}
+
+/// Complete lines appended to `path` since `pos`, or `None` if there are none.
+fn read_new_lines(path: &Path, pos: &mut u64, partial: &mut String) -> Option<Vec<LogLine>> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    if len < *pos {
+        *pos = 0;
+        partial.clear();
+    }
+    if len == *pos || file.seek(SeekFrom::Start(*pos)).is_err() {
+        return None;
+    }
+
+    let mut buf = Vec::new();
+    if let Ok(n) = file.read_to_end(&mut buf) {
+        *pos += n as u64;
+    }
+    partial.push_str(&String::from_utf8_lossy(&buf));
+
+    let end = partial.rfind('\n')?;
+    let lines = partial[..end].lines().map(parse_line).collect();
+    partial.drain(..=end);
+    Some(lines)
+}
+
+/// Open (or focus) the log viewer window and make sure lines are streaming.
+pub fn show_window(app: &AppHandle) -> AppResult<()> {
+    if let Some(win) = app.get_webview_window("logs") {
+        let _ = win.show();
+        let _ = win.set_focus();
+    } else {
+        WebviewWindowBuilder::new(app, "logs", WebviewUrl::App("logs.html".into()))
+            .title("OpenClaw Gateway Logs")
+            .inner_size(900.0, 600.0)
+            .build()
+            .map_err(|e| {
+                AppError::new(ErrorKind::Window, "Failed to open log window").with_source(e)
+            })?;
+    }
+
+    start_follower(app.clone());
+    Ok(())
+}
+
+/// Append a marker line so each gateway run is easy to find in the log.
+pub fn write_session_marker(path: &Path, pid: u32, bin: &str, version: Option<&str>) {
+    let line = format!(
+        "{} INFO [openclaw-desktop] gateway session started pid={} bin={} version={}\n",
+        humantime::format_rfc3339_seconds(SystemTime::now()),
+        pid,
+        bin,
+        version.unwrap_or("unknown")
+    );
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(line.as_bytes()));
+    if let Err(e) = result {
+        warn!(error = %e, "failed to write gateway session marker");
+    }
+}
+
+/// Check the log sizes/ages once a minute while the app runs.
+pub fn start_rotation_monitor() {
+    std::thread::spawn(|| loop {
+        std::thread::sleep(ROTATION_INTERVAL);
+        for inst in instance::all() {
+            rotate_if_needed(&inst.log_path);
+        }
+    });
+}
+
+/// Rotate a gateway log if it is too large or too old, and prune old segments.
+pub fn rotate_if_needed(path: &Path) {
+    rotate_with(path, &settings::load_settings().logs);
+}
+
+fn rotate_with(path: &Path, cfg: &LogSettings) {
+    let max_age = Duration::from_secs(cfg.max_age_days * 24 * 60 * 60);
+
+    prune_segments(path, cfg, max_age);
+
+    let Ok(meta) = fs::metadata(path) else {
+        return;
+    };
+    if meta.len() == 0 {
+        return;
+    }
+
+    let too_big = meta.len() > cfg.max_size_mb * 1024 * 1024;
+    // The active file is truncated in place and segment 1 may be pruned or
+    // never written, so its age is measured from a stamp touched on every
+    // rotation. Without a stamp yet, the age starts now.
+    let stamp = stamp_path(path);
+    let too_old = match fs::metadata(&stamp).and_then(|m| m.modified()) {
+        Ok(rotated) => rotated.elapsed().is_ok_and(|age| age > max_age),
+        Err(_) => {
+            touch(&stamp);
+            false
+        }
+    };
+
+    if too_big || too_old {
+        if let Err(e) = rotate(path, cfg) {
+            warn!(path = %path.display(), error = %e, "failed to rotate gateway log");
+        }
+    }
+}
+
+/// `desktop-gateway.log` -> `desktop-gateway.log.rotated`, whose mtime is
+/// the time of the last rotation.
+fn stamp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.rotated", name))
+}
+
+fn touch(path: &Path) {
+    let result = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .and_then(|f| f.set_modified(SystemTime::now()));
+    if let Err(e) = result {
+        warn!(path = %path.display(), error = %e, "failed to record log rotation time");
+    }
+}
+
+/// `desktop-gateway.log` -> `desktop-gateway.log.<n>.gz`
+fn segment_path(path: &Path, n: usize) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}.gz", name, n))
+}
+
+fn rotate(path: &Path, cfg: &LogSettings) -> io::Result<()> {
+    if cfg.max_files > 0 {
+        let _ = fs::remove_file(segment_path(path, cfg.max_files));
+        for n in (1..cfg.max_files).rev() {
+            let from = segment_path(path, n);
+            if from.exists() {
+                fs::rename(&from, segment_path(path, n + 1))?;
+            }
+        }
+
+        let mut input = File::open(path)?;
+        let mut encoder =
+            GzEncoder::new(File::create(segment_path(path, 1))?, Compression::default());
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?;
+    }
+
+    // Copy-truncate: the gateway keeps its O_APPEND descriptor, so truncating
+    // in place lets it carry on writing without being restarted.
+    OpenOptions::new().write(true).open(path)?.set_len(0)?;
+    touch(&stamp_path(path));
+    info!(path = %path.display(), "rotated gateway log");
+    Ok(())
+}
+
+fn prune_segments(path: &Path, cfg: &LogSettings, max_age: Duration) {
+    // Walk a little past max_files to clean up after the setting is lowered
+    for n in 1..=cfg.max_files + 20 {
+        let segment = segment_path(path, n);
+        let Ok(meta) = fs::metadata(&segment) else {
+            continue;
+        };
+        let expired = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age > max_age);
+        if n > cfg.max_files || expired {
+            if let Err(e) = fs::remove_file(&segment) {
+                warn!(path = %segment.display(), error = %e, "failed to remove log segment");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "openclaw-logs-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("desktop-gateway.log")
+    }
+
+    fn age(path: &Path, days: u64) {
+        let then = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(then)
+            .unwrap();
+    }
+
+    fn len(path: &Path) -> u64 {
+        fs::metadata(path).unwrap().len()
+    }
+
+    #[test]
+    fn old_log_rotates_once_without_segments() {
+        let log = scratch("no-segments");
+        let cfg = LogSettings {
+            max_files: 0,
+            ..LogSettings::default()
+        };
+        fs::write(&log, "first\n").unwrap();
+        rotate_with(&log, &cfg);
+        assert_eq!(len(&log), 6, "a fresh log has no age yet");
+
+        age(&stamp_path(&log), 30);
+        rotate_with(&log, &cfg);
+        assert_eq!(len(&log), 0);
+        assert!(!segment_path(&log, 1).exists());
+
+        fs::write(&log, "second\n").unwrap();
+        rotate_with(&log, &cfg);
+        assert_eq!(len(&log), 7, "rotated again right after rotating");
+        let _ = fs::remove_dir_all(log.parent().unwrap());
+    }
+
+    #[test]
+    fn pruning_the_newest_segment_does_not_restart_rotation() {
+        let log = scratch("pruned");
+        let cfg = LogSettings::default();
+        fs::write(&log, "first\n").unwrap();
+        rotate(&log, &cfg).unwrap();
+        assert!(segment_path(&log, 1).exists());
+
+        // Segment 1 expires and is pruned; the log itself was just rotated
+        age(&segment_path(&log, 1), 30);
+        fs::write(&log, "second\n").unwrap();
+        rotate_with(&log, &cfg);
+        assert!(!segment_path(&log, 1).exists());
+        assert_eq!(len(&log), 7);
+        let _ = fs::remove_dir_all(log.parent().unwrap());
+    }
+}
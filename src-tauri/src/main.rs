@@ -1,6 +1,68 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use openclaw_desktop::CliOverrides;
+use std::path::PathBuf;
+use std::process::exit;
+
+const USAGE: &str = "Usage: openclaw-desktop [--config <path>] [--port <port>]
+
+Options:
+  --config <path>  Use this openclaw.json instead of ~/.openclaw/openclaw.json
+                   (overrides $OPENCLAW_CONFIG)
+  --port <port>    Run the gateway on this port instead of gateway.port
+                   (overrides $OPENCLAW_GATEWAY_PORT)
+  -h, --help       Print this help";
+
+/// Parse `--config` and `--port`, accepting both `--flag value` and
+/// `--flag=value`. Unknown arguments are left alone, since the OS or a
+/// launcher may pass its own.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<CliOverrides, String> {
+    let mut overrides = CliOverrides::default();
+
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg, None),
+        };
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} requires a value", flag))
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            "--config" => overrides.config = Some(PathBuf::from(value()?)),
+            "--port" => {
+                let raw = value()?;
+                let port = raw
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|p| *p > 0)
+                    .ok_or_else(|| format!("invalid port \"{}\"", raw))?;
+                overrides.port = Some(port);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(overrides)
+}
+
 fn main() {
-    openclaw_desktop::run();
+    let overrides = match parse_args(std::env::args().skip(1)) {
+        Ok(overrides) => overrides,
+        Err(e) => {
+            eprintln!("openclaw-desktop: {}\n\n{}", e, USAGE);
+            exit(2);
+        }
+    };
+    openclaw_desktop::run(overrides);
 }
@@ -0,0 +1,325 @@
+//! Proxy auto-config: fetching PAC scripts, WPAD discovery and evaluating
+//! `FindProxyForURL`.
+//!
+//! PAC scripts come from the network, so they run in an embedded QuickJS
+//! engine rather than Node.js. The engine has no file, process or network
+//! access; all a script can reach are the standard PAC helper functions,
+//! and it runs under a time and memory limit.
+
+use reqwest::Url;
+use rquickjs::{CatchResultExt, Coerced, Context, Function, Runtime};
+use std::fs;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+use crate::config;
+use crate::error::{AppError, AppResult, ErrorKind};
+
+/// Time a script gets to load and answer for all URLs.
+const EVAL_TIMEOUT: Duration = Duration::from_secs(2);
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+const STACK_LIMIT: usize = 512 * 1024;
+
+/// The PAC helpers that need no host access. `dnsResolve` and
+/// `myIpAddress` are provided by `evaluate`. `dateRange` and `timeRange`
+/// are treated as always matching.
+const HELPERS: &str = r#"
+function dnsResolve(host) {
+  var ip = __dnsResolve(String(host));
+  return ip === undefined ? null : ip;
+}
+
+function myIpAddress() {
+  return __myIpAddress();
+}
+
+function ipToInt(ip) {
+  return ip.split(".").reduce(function (n, octet) { return n * 256 + Number(octet); }, 0);
+}
+
+function isPlainHostName(host) {
+  return host.indexOf(".") < 0;
+}
+
+function dnsDomainIs(host, domain) {
+  host = host.toLowerCase();
+  domain = domain.toLowerCase();
+  return host.length >= domain.length && host.substring(host.length - domain.length) === domain;
+}
+
+function localHostOrDomainIs(host, hostdom) {
+  return host === hostdom || (host.indexOf(".") < 0 && hostdom.indexOf(host + ".") === 0);
+}
+
+function isResolvable(host) {
+  return dnsResolve(host) !== null;
+}
+
+function isInNet(host, pattern, mask) {
+  var ip = dnsResolve(host);
+  if (ip === null || !/^\d+\.\d+\.\d+\.\d+$/.test(ip)) return false;
+  var m = ipToInt(mask);
+  return ((ipToInt(ip) & m) >>> 0) === ((ipToInt(pattern) & m) >>> 0);
+}
+
+function dnsDomainLevels(host) {
+  return host.split(".").length - 1;
+}
+
+function shExpMatch(str, pattern) {
+  var re = pattern
+    .replace(/[.+^${}()|[\]\\]/g, "\\$&")
+    .replace(/\*/g, ".*")
+    .replace(/\?/g, ".");
+  return new RegExp("^" + re + "$").test(str);
+}
+
+var DAYS = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+function weekdayRange(wd1, wd2, gmt) {
+  var utc = wd2 === "GMT" || gmt === "GMT";
+  var today = utc ? new Date().getUTCDay() : new Date().getDay();
+  var from = DAYS.indexOf(wd1);
+  var to = DAYS.indexOf(wd2) < 0 ? from : DAYS.indexOf(wd2);
+  return from <= to ? today >= from && today <= to : today >= from || today <= to;
+}
+
+function dateRange() {
+  return true;
+}
+
+function timeRange() {
+  return true;
+}
+"#;
+
+fn pac_error(message: impl Into<String>) -> AppError {
+    AppError::new(ErrorKind::CommandFailed, message)
+}
+
+/// Load a PAC script from an http(s) URL, a `file://` URL or a path.
+pub fn fetch(location: &str) -> AppResult<String> {
+    if let Some(path) = location
+        .strip_prefix("file://")
+        .or(location.starts_with('/').then_some(location))
+    {
+        return fs::read_to_string(path)
+            .map_err(|e| pac_error(format!("Failed to read PAC file {}", path)).with_source(e));
+    }
+
+    // The PAC server itself must be reached directly
+    let client = reqwest::blocking::Client::builder()
+        .no_proxy()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| AppError::internal("Failed to create HTTP client").with_source(e))?;
+    client
+        .get(location)
+        .send()
+        .and_then(|r| r.error_for_status())
+        .and_then(|r| r.text())
+        .map_err(|e| {
+            pac_error(format!("Failed to download PAC script {}", location)).with_source(e)
+        })
+}
+
+/// Find a PAC script through WPAD at `http://wpad.<domain>/wpad.dat`, for
+/// the DNS domain configured in /etc/resolv.conf only. Parent domains and
+/// a bare `wpad` host are not tried, since whoever controls those names
+/// outside the network would get to choose the proxy.
+/// Returns the URL that answered and the script.
+pub fn discover() -> Option<(String, String)> {
+    let conf = fs::read_to_string("/etc/resolv.conf").ok()?;
+    let directive = |name: &str| {
+        conf.lines()
+            .find_map(|l| l.trim().strip_prefix(name))
+            .and_then(|domains| domains.split_whitespace().next())
+            .map(|d| d.trim_end_matches('.').to_string())
+    };
+    let Some(domain) = directive("domain ").or_else(|| directive("search ")) else {
+        debug!("no DNS domain configured, skipping WPAD");
+        return None;
+    };
+
+    // A single label is a top-level domain; wpad.com is nobody's proxy
+    if domain.split('.').filter(|l| !l.is_empty()).count() < 2 {
+        debug!(%domain, "DNS domain too short for WPAD");
+        return None;
+    }
+
+    let url = format!("http://wpad.{}/wpad.dat", domain);
+    match fetch(&url) {
+        Ok(script) if script.contains("FindProxyForURL") => {
+            info!(%url, "found PAC script through WPAD");
+            Some((url, script))
+        }
+        _ => {
+            debug!(%url, "no WPAD script");
+            None
+        }
+    }
+}
+
+/// Run `FindProxyForURL` of `script` for each of `urls`.
+pub fn evaluate(script: &str, urls: &[String]) -> AppResult<Vec<String>> {
+    let engine_error =
+        |e: rquickjs::Error| AppError::internal("Failed to set up the PAC engine").with_source(e);
+
+    let runtime = Runtime::new().map_err(engine_error)?;
+    runtime.set_memory_limit(MEMORY_LIMIT);
+    runtime.set_max_stack_size(STACK_LIMIT);
+    let deadline = Instant::now() + EVAL_TIMEOUT;
+    runtime.set_interrupt_handler(Some(Box::new(move || Instant::now() > deadline)));
+    let context = Context::full(&runtime).map_err(engine_error)?;
+
+    context.with(|ctx| {
+        let globals = ctx.globals();
+        let dns_resolve =
+            Function::new(ctx.clone(), |host: String| resolve_ipv4(&host)).map_err(engine_error)?;
+        let my_ip = Function::new(ctx.clone(), || {
+            config::primary_lan_addr()
+                .filter(IpAddr::is_ipv4)
+                .map_or("127.0.0.1".to_string(), |ip| ip.to_string())
+        })
+        .map_err(engine_error)?;
+        globals
+            .set("__dnsResolve", dns_resolve)
+            .map_err(engine_error)?;
+        globals.set("__myIpAddress", my_ip).map_err(engine_error)?;
+        ctx.eval::<(), _>(HELPERS).map_err(engine_error)?;
+
+        let script_error = |e| pac_error("PAC script failed").with_source(e);
+        ctx.eval::<(), _>(script)
+            .catch(&ctx)
+            .map_err(|e| script_error(e.to_string()))?;
+        let find: Function = globals
+            .get("FindProxyForURL")
+            .map_err(|_| pac_error("PAC script does not define FindProxyForURL"))?;
+
+        urls.iter()
+            .map(|url| {
+                let host = Url::parse(url)
+                    .ok()
+                    .and_then(|u| u.host_str().map(str::to_string))
+                    .unwrap_or_default();
+                find.call::<_, Coerced<String>>((url.as_str(), host))
+                    .catch(&ctx)
+                    .map(|result| result.0)
+                    .map_err(|e| script_error(e.to_string()))
+            })
+            .collect()
+    })
+}
+
+/// First IPv4 address of `host`, which may already be one.
+fn resolve_ipv4(host: &str) -> Option<String> {
+    (host, 0)
+        .to_socket_addrs()
+        .ok()?
+        .map(|addr| addr.ip())
+        .find(IpAddr::is_ipv4)
+        .map(|ip| ip.to_string())
+}
+
+/// First usable entry of a `FindProxyForURL` result such as
+/// `PROXY proxy:3128; SOCKS5 socks:1080; DIRECT`, as a proxy URL.
+/// `None` means a direct connection.
+pub fn parse_result(result: &str) -> Option<String> {
+    for entry in result.split(';') {
+        let mut parts = entry.split_whitespace();
+        let Some(kind) = parts.next() else {
+            continue;
+        };
+        let scheme = match kind.to_ascii_uppercase().as_str() {
+            "DIRECT" => return None,
+            "PROXY" | "HTTP" => "http",
+            "HTTPS" => "https",
+            "SOCKS" | "SOCKS5" => "socks5",
+            "SOCKS4" => "socks4",
+            _ => continue,
+        };
+        if let Some(addr) = parts.next() {
+            return Some(format!("{}://{}", scheme, addr));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_proxy(script: &str, url: &str) -> AppResult<String> {
+        evaluate(script, &[url.to_string()]).map(|mut r| r.remove(0))
+    }
+
+    #[test]
+    fn evaluates_with_pac_helpers() {
+        let script = r#"
+            function FindProxyForURL(url, host) {
+              if (isPlainHostName(host) || dnsDomainIs(host, ".corp.example")) return "DIRECT";
+              if (shExpMatch(url, "https://*.openai.com/*")) return "PROXY proxy:3128; DIRECT";
+              return "SOCKS5 socks:1080";
+            }
+        "#;
+        let urls = [
+            "http://intranet/".to_string(),
+            "https://wiki.corp.example/".to_string(),
+            "https://api.openai.com/v1".to_string(),
+            "https://example.org/".to_string(),
+        ];
+        assert_eq!(
+            evaluate(script, &urls).unwrap(),
+            [
+                "DIRECT",
+                "DIRECT",
+                "PROXY proxy:3128; DIRECT",
+                "SOCKS5 socks:1080"
+            ]
+        );
+    }
+
+    #[test]
+    fn scripts_cannot_reach_the_host() {
+        let script = r#"
+            function FindProxyForURL(url, host) {
+              var global = dnsResolve.constructor("return this")();
+              return [typeof process, typeof require, typeof global.std, typeof global.os].join();
+            }
+        "#;
+        assert_eq!(
+            find_proxy(script, "https://example.org/").unwrap(),
+            "undefined,undefined,undefined,undefined"
+        );
+    }
+
+    #[test]
+    fn runaway_scripts_are_stopped() {
+        let started = Instant::now();
+        let script = "function FindProxyForURL(url, host) { while (true) {} }";
+        assert!(find_proxy(script, "https://example.org/").is_err());
+        assert!(started.elapsed() < EVAL_TIMEOUT * 2);
+    }
+
+    #[test]
+    fn script_errors_are_reported() {
+        let err = find_proxy("function FindProxyForURL( {", "https://example.org/").unwrap_err();
+        assert_eq!(err.message, "PAC script failed");
+        assert!(find_proxy("var x = 1;", "https://example.org/").is_err());
+    }
+
+    #[test]
+    fn parses_first_usable_entry() {
+        assert_eq!(
+            parse_result("PROXY proxy:3128; DIRECT").as_deref(),
+            Some("http://proxy:3128")
+        );
+        assert_eq!(
+            parse_result("BOGUS x; SOCKS socks:1080").as_deref(),
+            Some("socks5://socks:1080")
+        );
+        assert_eq!(parse_result("DIRECT; PROXY proxy:3128"), None);
+        assert_eq!(parse_result(""), None);
+    }
+}
@@ -0,0 +1,155 @@
+use serde::Serialize;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, Url};
+use tracing::{info, warn};
+
+use crate::connection::{self, Connection};
+use crate::error::{AppError, AppResult, ErrorKind};
+use crate::gateway;
+use crate::settings::{self, ConnectionSettings, Profile, WindowState};
+use crate::tray;
+
+/// URL of the app's own pages, so the main window can be brought back from
+/// the gateway UI after switching.
+static HOME_URL: OnceLock<Url> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileSummary {
+    pub name: String,
+    /// "local" or "remote".
+    pub mode: String,
+    pub url: Option<String>,
+    /// Gateway instance a local profile runs, if not the default.
+    pub instance: Option<String>,
+    /// URL with token to open the gateway UI; `None` if it can't be resolved
+    /// (e.g. no openclaw.json yet for a local profile).
+    pub full_url: Option<String>,
+    pub active: bool,
+}
+
+pub fn set_home_url(url: Url) {
+    let _ = HOME_URL.set(url);
+}
+
+pub fn list() -> Vec<ProfileSummary> {
+    let s = settings::load_settings();
+    s.profiles
+        .iter()
+        .map(|p| {
+            let conn = Connection::from_settings(p.connection.clone()).ok();
+            let (mode, url, instance) = match &p.connection {
+                ConnectionSettings::Local { instance, .. } => (
+                    "local",
+                    conn.as_ref().map(|c| c.base_url()),
+                    instance.clone(),
+                ),
+                ConnectionSettings::Remote { url, .. } => ("remote", Some(url.clone()), None),
+            };
+            ProfileSummary {
+                name: p.name.clone(),
+                mode: mode.to_string(),
+                url,
+                instance,
+                full_url: conn.map(|c| c.full_url()),
+                active: s.active_profile.as_deref() == Some(p.name.as_str()),
+            }
+        })
+        .collect()
+}
+
+/// Add a profile, or replace the one with the same name.
+pub fn save(profile: Profile) -> AppResult<()> {
+    if profile.name.trim().is_empty() {
+        return Err(AppError::new(
+            ErrorKind::InvalidInput,
+            "Profile name cannot be empty",
+        ));
+    }
+    connection::validate(&profile.connection)?;
+
+    let mut s = settings::load_settings();
+    match s.profiles.iter_mut().find(|p| p.name == profile.name) {
+        Some(existing) => {
+            existing.connection = profile.connection;
+        }
+        None => s.profiles.push(profile),
+    }
+    settings::save_settings(&s)
+}
+
+pub fn delete(name: &str) -> AppResult<()> {
+    let mut s = settings::load_settings();
+    s.profiles.retain(|p| p.name != name);
+    if s.active_profile.as_deref() == Some(name) {
+        s.active_profile = None;
+    }
+    settings::save_settings(&s)
+}
+
+/// Make `name` the active profile (`None` for the default connection),
+/// swap window geometry and reconnect. Blocks while the gateway starts.
+pub fn switch(app: &AppHandle, name: Option<&str>) -> AppResult<()> {
+    let mut s = settings::load_settings();
+    if let Some(name) = name {
+        if !s.profiles.iter().any(|p| p.name == name) {
+            return Err(AppError::new(
+                ErrorKind::InvalidInput,
+                format!("No profile named \"{}\"", name),
+            ));
+        }
+    }
+
+    let win = app.get_webview_window("main");
+
+    // Remember how the window looked for the profile (or lack of one)
+    // we're leaving
+    if let Some(win) = &win {
+        *s.active_window_mut() = capture_window(win);
+    }
+
+    s.active_profile = name.map(str::to_string);
+    let restore = *s.active_window_mut();
+    settings::save_settings(&s)?;
+    info!(profile = name.unwrap_or("default"), "switched profile");
+
+    if let (Some(win), Some(state)) = (&win, restore) {
+        restore_window(win, state);
+    }
+
+    gateway::ensure_started();
+    tray::refresh_profiles(app);
+    let _ = app.emit("profile-changed", name);
+
+    // Reload the connection screen so it picks up the new gateway
+    if let (Some(win), Some(home)) = (&win, HOME_URL.get()) {
+        if let Ok(url) = home.join("index.html") {
+            if let Err(e) = win.navigate(url) {
+                warn!(error = %e, "failed to reload main window");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn capture_window(win: &tauri::WebviewWindow) -> Option<WindowState> {
+    let pos = win.outer_position().ok()?;
+    let size = win.inner_size().ok()?;
+    Some(WindowState {
+        x: pos.x,
+        y: pos.y,
+        width: size.width,
+        height: size.height,
+        maximized: win.is_maximized().unwrap_or(false),
+    })
+}
+
+fn restore_window(win: &tauri::WebviewWindow, state: WindowState) {
+    if state.maximized {
+        let _ = win.maximize();
+        return;
+    }
+    let _ = win.unmaximize();
+    let _ = win.set_size(PhysicalSize::new(state.width, state.height));
+    let _ = win.set_position(PhysicalPosition::new(state.x, state.y));
+}
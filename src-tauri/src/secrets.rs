@@ -0,0 +1,484 @@
+//! API keys, channel tokens and proxy passwords, kept out of openclaw.json.
+//!
+//! The config refers to a secret by handle, as `"${OPENCLAW_SECRET_...}"`.
+//! The value lives in the desktop keyring (Secret Service, through
+//! libsecret's `secret-tool`) or, where no keyring is running, in an
+//! AES-GCM encrypted file next to the desktop settings. When the gateway
+//! is spawned every referenced secret is put into its environment, where
+//! OpenClaw substitutes it into the config.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Mutex, OnceLock};
+use tracing::{info, warn};
+
+use crate::backups;
+use crate::config;
+use crate::error::{AppError, AppResult, ErrorKind};
+
+/// Prefix of every handle, which doubles as the gateway env var name.
+pub const HANDLE_PREFIX: &str = "OPENCLAW_SECRET_";
+
+/// Secret Service attribute identifying this app's items.
+const SERVICE: &str = "openclaw-desktop";
+
+const NONCE_LEN: usize = 12;
+
+/// Serializes access to the encrypted file.
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    SecretService,
+    EncryptedFile,
+}
+
+/// Handle for the secret at a dotted config path, e.g.
+/// `providers.minimax.apiKey` -> `OPENCLAW_SECRET_PROVIDERS__MINIMAX__API_4BEY`.
+///
+/// Lowercase letters and digits are upper-cased, dots become `__` and any
+/// other byte becomes `_` and two hex digits, so no two paths share a
+/// handle and the result is still a valid variable name.
+pub fn handle_for(path: &str) -> String {
+    let mut name = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'a'..=b'z' | b'0'..=b'9' => name.push(byte.to_ascii_uppercase() as char),
+            b'.' => name.push_str("__"),
+            _ => name.push_str(&format!("_{:02X}", byte)),
+        }
+    }
+    format!("{}{}", HANDLE_PREFIX, name)
+}
+
+/// The config value that refers to `handle`.
+pub fn reference(handle: &str) -> String {
+    format!("${{{}}}", handle)
+}
+
+/// The handle a config value refers to, if it is a secret reference.
+pub fn handle_in(value: &str) -> Option<&str> {
+    value
+        .strip_prefix("${")
+        .and_then(|v| v.strip_suffix('}'))
+        .filter(|h| h.starts_with(HANDLE_PREFIX))
+}
+
+/// The keyring if one answers, otherwise the encrypted file.
+pub fn backend() -> Backend {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    let available = *AVAILABLE.get_or_init(|| {
+        // A lookup of a missing item fails quietly; a missing daemon or
+        // D-Bus session fails with a message
+        let probe = Command::new("secret-tool")
+            .args(["lookup", "service", SERVICE, "handle", "probe"])
+            .stdin(Stdio::null())
+            .output();
+        match probe {
+            Ok(output) => output.status.success() || output.stderr.is_empty(),
+            Err(_) => false,
+        }
+    });
+    if available {
+        Backend::SecretService
+    } else {
+        Backend::EncryptedFile
+    }
+}
+
+/// Store `value` under `handle`, replacing any previous value.
+#[tracing::instrument(skip(value), err)]
+pub fn set(handle: &str, value: &str) -> AppResult<()> {
+    let result = match backend() {
+        Backend::SecretService => keyring_set(handle, value),
+        Backend::EncryptedFile => file_update(|secrets| {
+            secrets.insert(handle.to_string(), value.to_string());
+        }),
+    };
+    result.map_err(|e| {
+        AppError::new(
+            ErrorKind::SecretStore,
+            format!("Failed to store secret {}", handle),
+        )
+        .with_source(e)
+    })
+}
+
+/// Look up `handle`, in the keyring first and then in the encrypted file,
+/// so secrets stored before a keyring became available still resolve.
+pub fn get(handle: &str) -> Option<String> {
+    if backend() == Backend::SecretService {
+        if let Some(value) = keyring_get(handle) {
+            return Some(value);
+        }
+    }
+    match file_read() {
+        Ok(mut secrets) => secrets.remove(handle),
+        Err(e) => {
+            warn!(error = %e, "failed to read encrypted secrets");
+            None
+        }
+    }
+}
+
+/// Delete the secret stored under `handle`, if any.
+#[tracing::instrument(err)]
+pub fn remove(handle: &str) -> AppResult<()> {
+    let result = match backend() {
+        Backend::SecretService => keyring_clear(handle),
+        Backend::EncryptedFile => file_update(|secrets| {
+            secrets.remove(handle);
+        }),
+    };
+    result.map_err(|e| {
+        AppError::new(
+            ErrorKind::SecretStore,
+            format!("Failed to remove secret {}", handle),
+        )
+        .with_source(e)
+    })
+}
+
+fn keyring_set(handle: &str, value: &str) -> io::Result<()> {
+    // The value goes through stdin, never the command line
+    let mut child = Command::new("secret-tool")
+        .args([
+            "store",
+            &format!("--label=OpenClaw {}", handle),
+            "service",
+            SERVICE,
+            "handle",
+            handle,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(value.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn keyring_get(handle: &str) -> Option<String> {
+    Command::new("secret-tool")
+        .args(["lookup", "service", SERVICE, "handle", handle])
+        .stdin(Stdio::null())
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+}
+
+fn keyring_clear(handle: &str) -> io::Result<()> {
+    let output = Command::new("secret-tool")
+        .args(["clear", "service", SERVICE, "handle", handle])
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() && !output.stderr.is_empty() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn secrets_dir() -> io::Result<PathBuf> {
+    dirs::config_dir()
+        .map(|d| d.join("openclaw-desktop"))
+        .ok_or_else(|| io::Error::other("could not determine config directory"))
+}
+
+/// Key of the encrypted file, created on first use. It only keeps the
+/// secrets out of openclaw.json and `ps`; anyone who can read the key file
+/// can decrypt them, which is why the keyring comes first.
+fn file_key(dir: &Path) -> io::Result<Key<Aes256Gcm>> {
+    let path = dir.join("secrets.key");
+    match fs::read(&path) {
+        Ok(bytes) if bytes.len() == 32 => return Ok(*Key::<Aes256Gcm>::from_slice(&bytes)),
+        Ok(_) => return Err(io::Error::other(format!("{} is corrupt", path.display()))),
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        Err(_) => {}
+    }
+    let key = Aes256Gcm::generate_key(OsRng);
+    fs::create_dir_all(dir)?;
+    config::write_atomic(&path, key.as_slice())?;
+    Ok(key)
+}
+
+fn file_read() -> io::Result<BTreeMap<String, String>> {
+    let _guard = FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    read_secrets_file(&secrets_dir()?)
+}
+
+fn read_secrets_file(dir: &Path) -> io::Result<BTreeMap<String, String>> {
+    let data = match fs::read(dir.join("secrets.bin")) {
+        Ok(data) => data,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(e),
+    };
+    if data.len() < NONCE_LEN {
+        return Err(io::Error::other("secrets file is truncated"));
+    }
+
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new(&file_key(dir)?);
+    let plain = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| io::Error::other("secrets file cannot be decrypted with secrets.key"))?;
+    serde_json::from_slice(&plain).map_err(io::Error::other)
+}
+
+fn file_update(f: impl FnOnce(&mut BTreeMap<String, String>)) -> io::Result<()> {
+    let _guard = FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = secrets_dir()?;
+    let mut secrets = read_secrets_file(&dir)?;
+    f(&mut secrets);
+    write_secrets_file(&dir, &secrets)
+}
+
+fn write_secrets_file(dir: &Path, secrets: &BTreeMap<String, String>) -> io::Result<()> {
+    let cipher = Aes256Gcm::new(&file_key(dir)?);
+    let nonce = Aes256Gcm::generate_nonce(OsRng);
+    let plain = serde_json::to_vec(secrets).map_err(io::Error::other)?;
+    let ciphertext = cipher
+        .encrypt(&nonce, plain.as_slice())
+        .map_err(|_| io::Error::other("failed to encrypt secrets"))?;
+
+    let mut data = nonce.to_vec();
+    data.extend_from_slice(&ciphertext);
+    config::write_atomic(&dir.join("secrets.bin"), &data)
+}
+
+/// Every secret handle referenced anywhere in `doc`.
+pub fn referenced_handles(doc: &Value) -> Vec<String> {
+    fn walk(value: &Value, out: &mut Vec<String>) {
+        match value {
+            Value::String(s) => {
+                if let Some(handle) = handle_in(s) {
+                    if !out.iter().any(|h| h == handle) {
+                        out.push(handle.to_string());
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|v| walk(v, out)),
+            Value::Object(map) => map.values().for_each(|v| walk(v, out)),
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    walk(doc, &mut out);
+    out
+}
+
+/// Environment for a gateway running the config at `config_path`: each
+/// referenced handle mapped to its secret. Missing secrets are logged and
+/// left out, so the gateway reports them like any unset variable.
+pub fn gateway_env(config_path: &Path) -> Vec<(String, String)> {
+    let Ok(doc) = config::read_document(config_path) else {
+        return Vec::new();
+    };
+    referenced_handles(&doc)
+        .into_iter()
+        .filter_map(|handle| match get(&handle) {
+            Some(value) => Some((handle, value)),
+            None => {
+                warn!(%handle, "secret referenced by the config is not stored");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Secrets stored ahead of the config write that refers to them, so they
+/// can be taken back if that write fails.
+#[derive(Default)]
+pub struct Staged(Vec<(String, Option<String>)>);
+
+impl Staged {
+    /// Store `value` as the secret for config path `path` and return the
+    /// reference to put in the config in its place.
+    pub fn store_for(&mut self, path: &str, value: &str) -> AppResult<String> {
+        let handle = handle_for(path);
+        let previous = get(&handle);
+        set(&handle, value)?;
+        self.0.push((handle.clone(), previous));
+        Ok(reference(&handle))
+    }
+
+    /// Put back what each handle held before, removing the new ones.
+    pub fn roll_back(self) {
+        for (handle, previous) in self.0.into_iter().rev() {
+            let result = match previous {
+                Some(value) => set(&handle, &value),
+                None => remove(&handle),
+            };
+            if let Err(e) = result {
+                warn!(%handle, error = %e, "failed to roll back secret");
+            }
+        }
+    }
+}
+
+/// Move plaintext provider API keys, channel tokens and the proxy password
+/// from the config at `config_path` into secret storage, replacing them
+/// with references, and take them out of the config's backups as well.
+/// Returns the dotted paths that were moved.
+#[tracing::instrument(err)]
+pub fn secure_config(config_path: &Path) -> AppResult<Vec<String>> {
+    let doc = config::read_document(config_path)?;
+    let mut staged = Staged::default();
+    // Config path segments, plaintext and reference of each moved secret
+    let mut moved: Vec<(Vec<String>, String, String)> = Vec::new();
+
+    let result = (|| -> AppResult<()> {
+        let mut patch = json!({});
+        for (section, key) in [("providers", "apiKey"), ("channels", "token")] {
+            let Some(entries) = doc.get(section).and_then(Value::as_object) else {
+                continue;
+            };
+            for (name, entry) in entries {
+                let Some(value) = entry.get(key).and_then(Value::as_str) else {
+                    continue;
+                };
+                if value.trim().is_empty() || handle_in(value).is_some() {
+                    continue;
+                }
+                let path = format!("{}.{}.{}", section, name, key);
+                let reference = staged.store_for(&path, value)?;
+                patch[section][name][key] = Value::String(reference.clone());
+                let segments = vec![section.to_string(), name.clone(), key.to_string()];
+                moved.push((segments, value.to_string(), reference));
+            }
+        }
+
+        if let Some(password) = doc.pointer("/proxy/password").and_then(Value::as_str) {
+            if !password.is_empty() && handle_in(password).is_none() {
+                let reference = staged.store_for("proxy.password", password)?;
+                patch["proxy"]["password"] = Value::String(reference.clone());
+                let segments = vec!["proxy".to_string(), "password".to_string()];
+                moved.push((segments, password.to_string(), reference));
+            }
+        }
+
+        if !moved.is_empty() {
+            config::update_config_at(config_path, &patch)?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        staged.roll_back();
+        return Err(e);
+    }
+    if moved.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    info!(count = moved.len(), backend = ?backend(), "moved secrets out of the config");
+
+    // The write itself backed up the plaintext config
+    let rewritten = backups::replace_values(config_path, &moved);
+    if rewritten > 0 {
+        info!(
+            count = rewritten,
+            "removed moved secrets from config backups"
+        );
+    }
+
+    Ok(moved
+        .into_iter()
+        .map(|(segments, _, _)| segments.join("."))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_are_readable_variable_names() {
+        assert_eq!(
+            handle_for("providers.minimax.apiKey"),
+            "OPENCLAW_SECRET_PROVIDERS__MINIMAX__API_4BEY"
+        );
+        assert_eq!(
+            handle_for("proxy.password"),
+            "OPENCLAW_SECRET_PROXY__PASSWORD"
+        );
+        let handle = handle_for("channels.my-bot_2.token");
+        assert!(handle
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'));
+    }
+
+    #[test]
+    fn distinct_paths_get_distinct_handles() {
+        let paths = [
+            "channels.a-b.token",
+            "channels.a_b.token",
+            "channels.a.b.token",
+            "channels.a__b.token",
+            "providers.x.apiKey",
+            "providers.x.apikey",
+            "providers.x.api_4Bey",
+            "providers.网关.apiKey",
+        ];
+        let handles: std::collections::BTreeSet<String> =
+            paths.iter().map(|p| handle_for(p)).collect();
+        assert_eq!(handles.len(), paths.len());
+    }
+
+    #[test]
+    fn references_round_trip() {
+        let handle = handle_for("proxy.password");
+        let value = reference(&handle);
+        assert_eq!(value, "${OPENCLAW_SECRET_PROXY__PASSWORD}");
+        assert_eq!(handle_in(&value), Some(handle.as_str()));
+    }
+
+    #[test]
+    fn only_secret_references_are_handles() {
+        assert_eq!(handle_in("${HOME}"), None);
+        assert_eq!(handle_in("OPENCLAW_SECRET_X"), None);
+        assert_eq!(handle_in("${OPENCLAW_SECRET_X"), None);
+        assert_eq!(handle_in("sk-${OPENCLAW_SECRET_X}"), None);
+        assert_eq!(handle_in("${OPENCLAW_SECRET_X}"), Some("OPENCLAW_SECRET_X"));
+    }
+
+    #[test]
+    fn encrypted_file_round_trip() {
+        let dir =
+            std::env::temp_dir().join(format!("openclaw-secrets-test-{}", std::process::id()));
+        assert!(read_secrets_file(&dir).unwrap().is_empty());
+
+        let secrets = BTreeMap::from([
+            ("OPENCLAW_SECRET_A".to_string(), "sk-plaintext".to_string()),
+            ("OPENCLAW_SECRET_B".to_string(), "密码".to_string()),
+        ]);
+        write_secrets_file(&dir, &secrets).unwrap();
+        assert_eq!(read_secrets_file(&dir).unwrap(), secrets);
+
+        let raw = fs::read(dir.join("secrets.bin")).unwrap();
+        assert!(!raw.windows(12).any(|w| w == b"sk-plaintext"));
+
+        // A different key must not decrypt it
+        fs::write(dir.join("secrets.key"), [7u8; 32]).unwrap();
+        assert!(read_secrets_file(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
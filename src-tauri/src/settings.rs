@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use tracing::warn;
+
+use crate::config;
+use crate::error::{AppError, AppResult, ErrorKind};
+
+/// Settings owned by the desktop app itself, kept apart from `openclaw.json`
+/// so the OpenClaw CLI never sees keys it doesn't understand.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DesktopSettings {
+    pub gateway: GatewaySettings,
+    pub logs: LogSettings,
+    pub logging: LoggingSettings,
+    /// Used when no profile is active.
+    pub connection: ConnectionSettings,
+    pub profiles: Vec<Profile>,
+    pub active_profile: Option<String>,
+    pub backups: BackupSettings,
+    /// Extra local gateways run next to the default one.
+    pub instances: Vec<InstanceSettings>,
+    /// Main window geometry last used with no profile active.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window: Option<WindowState>,
+}
+
+impl DesktopSettings {
+    /// Connection of the active profile, falling back to `connection`.
+    pub fn active_connection(&self) -> ConnectionSettings {
+        self.active_profile()
+            .map(|p| p.connection.clone())
+            .unwrap_or_else(|| self.connection.clone())
+    }
+
+    pub fn active_profile(&self) -> Option<&Profile> {
+        let name = self.active_profile.as_deref()?;
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn active_profile_mut(&mut self) -> Option<&mut Profile> {
+        let name = self.active_profile.clone()?;
+        self.profiles.iter_mut().find(|p| p.name == name)
+    }
+
+    /// Window geometry of the active profile, or `window` without one.
+    pub fn active_window_mut(&mut self) -> &mut Option<WindowState> {
+        let name = self.active_profile.clone();
+        match self.profiles.iter_mut().find(|p| Some(&p.name) == name.as_ref()) {
+            Some(profile) => &mut profile.window,
+            None => &mut self.window,
+        }
+    }
+}
+
+/// A named gateway definition the user can switch to from the tray.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub name: String,
+    pub connection: ConnectionSettings,
+    /// Main window geometry last used with this profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<WindowState>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+}
+
+/// A local gateway with its own state dir, holding its `openclaw.json` and log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceSettings {
+    pub name: String,
+    pub state_dir: PathBuf,
+    /// Start together with the default gateway when the app launches.
+    #[serde(default)]
+    pub autostart: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GatewaySettings {
+    /// What to do when a gateway is already running on the configured port.
+    pub policy: GatewayPolicy,
+    /// Seconds to wait after SIGTERM before SIGKILLing the gateway's process group.
+    pub shutdown_grace_secs: u64,
+    /// Restart the gateway when an external edit to openclaw.json changes
+    /// settings it only reads at startup. When off, the UI offers a restart.
+    pub restart_on_config_change: bool,
+    /// CA bundle passed to the gateway as `NODE_EXTRA_CA_CERTS`, e.g. the
+    /// certificate of a proxy that intercepts TLS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra_ca_certs: Option<PathBuf>,
+    /// Extra environment variables for the gateway, applied over the ones
+    /// the app sets for the proxy and Node.js.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+impl Default for GatewaySettings {
+    fn default() -> Self {
+        Self {
+            policy: GatewayPolicy::default(),
+            shutdown_grace_secs: 10,
+            restart_on_config_change: true,
+            extra_ca_certs: None,
+            env: BTreeMap::new(),
+        }
+    }
+}
+
+/// Rotation and retention of `desktop-gateway.log`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LogSettings {
+    /// Rotate the active log once it grows past this size.
+    pub max_size_mb: u64,
+    /// Number of gzipped segments to keep.
+    pub max_files: usize,
+    /// Rotate the active log and delete segments older than this.
+    pub max_age_days: u64,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            max_size_mb: 10,
+            max_files: 5,
+            max_age_days: 14,
+        }
+    }
+}
+
+impl LogSettings {
+    /// A limit of zero would rotate, and so empty, the log on every check.
+    fn clamp(&mut self) {
+        if self.max_size_mb == 0 || self.max_files == 0 || self.max_age_days == 0 {
+            warn!(?self, "log rotation limits must be at least 1, raising zeros to 1");
+        }
+        self.max_size_mb = self.max_size_mb.max(1);
+        self.max_files = self.max_files.max(1);
+        self.max_age_days = self.max_age_days.max(1);
+    }
+}
+
+/// Copies of openclaw.json taken before every write, in `backups/` next to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BackupSettings {
+    /// Number of backups to keep; older ones are deleted.
+    pub keep: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self { keep: 20 }
+    }
+}
+
+/// Which gateway the app connects to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase", rename_all_fields = "camelCase")]
+pub enum ConnectionSettings {
+    /// Run (or attach to) a gateway on this machine, configured by openclaw.json.
+    /// With no fields set that is the default instance; `instance` picks one
+    /// of `DesktopSettings::instances` instead. `config_path`, `port` and
+    /// `token` describe a gateway started outside the app, which is only
+    /// attached to.
+    Local {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        instance: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        config_path: Option<PathBuf>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        port: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    /// Use a gateway on another machine; nothing is spawned or killed locally.
+    Remote { url: String, token: String },
+}
+
+impl Default for ConnectionSettings {
+    fn default() -> Self {
+        ConnectionSettings::Local {
+            instance: None,
+            config_path: None,
+            port: None,
+            token: None,
+        }
+    }
+}
+
+impl ConnectionSettings {
+    /// A local gateway the app neither spawns nor kills.
+    pub fn is_external(&self) -> bool {
+        matches!(
+            self,
+            ConnectionSettings::Local { instance: None, config_path, port, token }
+                if config_path.is_some() || port.is_some() || token.is_some()
+        )
+    }
+}
+
+/// Diagnostics of the desktop app itself (not the gateway).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LoggingSettings {
+    /// One of "error", "warn", "info", "debug", "trace".
+    pub level: String,
+    /// Mirror log records to stderr, with journald priority prefixes when
+    /// running under systemd.
+    pub stderr: bool,
+}
+
+impl Default for LoggingSettings {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            stderr: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GatewayPolicy {
+    /// Reuse a healthy, compatible gateway read-only; spawn one only if none is running.
+    #[default]
+    Attach,
+    /// Kill any existing gateway (including the systemd unit) and spawn a fresh one.
+    Replace,
+    /// Always spawn our own gateway, but refuse to start if the port is already served.
+    Managed,
+}
+
+fn settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("openclaw-desktop").join("settings.json"))
+}
+
+/// Load desktop settings, falling back to defaults if the file is missing or invalid.
+pub fn load_settings() -> DesktopSettings {
+    let Some(path) = settings_path() else {
+        return DesktopSettings::default();
+    };
+
+    if !path.exists() {
+        return DesktopSettings::default();
+    }
+
+    match fs::read_to_string(&path).map(|s| serde_json::from_str::<DesktopSettings>(&s)) {
+        Ok(Ok(mut settings)) => {
+            settings.logs.clamp();
+            settings
+        }
+        Ok(Err(e)) => {
+            warn!(path = %path.display(), error = %e, "invalid desktop settings, using defaults");
+            DesktopSettings::default()
+        }
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "failed to read desktop settings, using defaults");
+            DesktopSettings::default()
+        }
+    }
+}
+
+pub fn save_settings(settings: &DesktopSettings) -> AppResult<()> {
+    let path = settings_path().ok_or_else(AppError::home_dir_unknown)?;
+    let write_error = |e: std::io::Error| {
+        AppError::new(ErrorKind::SettingsWrite, format!("Failed to write {}", path.display()))
+            .with_path(&path)
+            .with_source(e)
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(write_error)?;
+    }
+
+    let content = serde_json::to_string_pretty(settings)
+        .map_err(|e| AppError::internal("Failed to serialize settings").with_source(e))?;
+
+    config::write_atomic(&path, content.as_bytes()).map_err(write_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_log_limits_are_raised_to_one() {
+        let mut logs: LogSettings =
+            serde_json::from_str(r#"{"maxSizeMb": 0, "maxFiles": 0, "maxAgeDays": 0}"#).unwrap();
+        logs.clamp();
+        assert_eq!((logs.max_size_mb, logs.max_files, logs.max_age_days), (1, 1, 1));
+
+        let mut logs = LogSettings::default();
+        logs.clamp();
+        assert_eq!((logs.max_size_mb, logs.max_files, logs.max_age_days), (10, 5, 14));
+    }
+}
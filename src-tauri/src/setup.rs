@@ -1,8 +1,14 @@
 use serde::Serialize;
-use std::fs;
-use std::path::PathBuf;
 use std::process::Command;
 
+use crate::config;
+use crate::connection;
+use crate::instance;
+use crate::proxy::{self, ProxySettings};
+use crate::secrets;
+use crate::settings::{self, ConnectionSettings};
+use crate::error::{AppError, AppResult, ErrorKind};
+
 #[derive(Debug, Clone, Serialize)]
 pub struct PrereqStatus {
     pub node_installed: bool,
@@ -14,18 +20,6 @@ pub struct PrereqStatus {
     pub proxy_detected: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct ProxyInfo {
-    pub detected: bool,
-    pub http: String,
-    pub socks: String,
-    pub source: String,
-}
-
-fn openclaw_config_path() -> Option<PathBuf> {
-    dirs::home_dir().map(|h| h.join(".openclaw").join("openclaw.json"))
-}
-
 fn run_command_output(cmd: &str, args: &[&str]) -> Option<String> {
     Command::new(cmd)
         .args(args)
@@ -36,21 +30,39 @@ fn run_command_output(cmd: &str, args: &[&str]) -> Option<String> {
         .map(|s| s.trim().to_string())
 }
 
-fn gsettings_get(schema: &str, key: &str) -> Option<String> {
-    run_command_output("gsettings", &["get", schema, key])
-        .map(|s| s.trim_matches('\'').to_string())
-}
-
+/// Whether the gateway the app connects to still needs a config. Follows
+/// the active profile: a remote gateway needs no local openclaw.json, and a
+/// local one is checked for the config of the instance or file it uses.
 #[tauri::command]
 pub fn is_first_run() -> bool {
-    match openclaw_config_path() {
+    let config_path = match settings::load_settings().active_connection() {
+        ConnectionSettings::Remote { .. } => return false,
+        ConnectionSettings::Local {
+            config_path: Some(path),
+            ..
+        } => Some(path),
+        ConnectionSettings::Local { .. } => instance::get(&connection::main_instance())
+            .ok()
+            .map(|inst| inst.config_path),
+    };
+
+    match config_path {
         Some(path) => !path.exists(),
         None => true,
     }
 }
 
+/// Runs node, npm and openclaw and may evaluate a PAC script, so it stays
+/// off the main thread.
 #[tauri::command]
-pub fn check_prerequisites() -> PrereqStatus {
+#[tracing::instrument(err)]
+pub async fn check_prerequisites() -> AppResult<PrereqStatus> {
+    tauri::async_runtime::spawn_blocking(prerequisites)
+        .await
+        .map_err(AppError::task_join)
+}
+
+fn prerequisites() -> PrereqStatus {
     let node_version = run_command_output("node", &["--version"]).unwrap_or_default();
     let node_installed = !node_version.is_empty();
 
@@ -60,16 +72,16 @@ pub fn check_prerequisites() -> PrereqStatus {
     let openclaw_version = run_command_output("openclaw", &["--version"]).unwrap_or_default();
     let openclaw_installed = !openclaw_version.is_empty();
 
-    let config_exists = openclaw_config_path()
+    let config_exists = config::config_path()
         .map(|p| p.exists())
         .unwrap_or(false);
 
-    let proxy = detect_proxy_inner();
-    let proxy_detected = if proxy.detected {
-        Some(proxy.http.clone())
-    } else {
-        None
-    };
+    let proxy = proxy::resolve();
+    let proxy_detected = proxy
+        .http
+        .or(proxy.https)
+        .or(proxy.socks)
+        .map(|url| proxy::redact(&url));
 
     PrereqStatus {
         node_installed,
@@ -83,15 +95,16 @@ pub fn check_prerequisites() -> PrereqStatus {
 }
 
 #[tauri::command]
-pub async fn install_openclaw() -> Result<String, String> {
+#[tracing::instrument(err)]
+pub async fn install_openclaw() -> AppResult<String> {
     let output = tauri::async_runtime::spawn_blocking(|| {
         Command::new("npm")
             .args(["install", "-g", "openclaw"])
             .output()
     })
     .await
-    .map_err(|e| format!("Task join error: {}", e))?
-    .map_err(|e| format!("Failed to run npm: {}", e))?;
+    .map_err(AppError::task_join)?
+    .map_err(|e| AppError::new(ErrorKind::CommandFailed, "Failed to run npm").with_source(e))?;
 
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -99,76 +112,28 @@ pub async fn install_openclaw() -> Result<String, String> {
     if output.status.success() {
         Ok(format!("{}\n{}", stdout, stderr).trim().to_string())
     } else {
-        Err(format!(
-            "npm install failed (exit code {}):\n{}\n{}",
-            output.status.code().unwrap_or(-1),
-            stdout,
-            stderr
-        ))
-    }
-}
-
-fn detect_proxy_inner() -> ProxyInfo {
-    // Check environment variables first
-    if let Ok(proxy) = std::env::var("HTTP_PROXY").or_else(|_| std::env::var("http_proxy")) {
-        let socks = std::env::var("ALL_PROXY")
-            .or_else(|_| std::env::var("all_proxy"))
-            .unwrap_or_default();
-        return ProxyInfo {
-            detected: true,
-            http: proxy,
-            socks,
-            source: "env".to_string(),
-        };
-    }
-
-    // Fall back to GNOME gsettings
-    let mode = gsettings_get("org.gnome.system.proxy", "mode");
-    if mode.as_deref() == Some("manual") {
-        let host = gsettings_get("org.gnome.system.proxy.http", "host").unwrap_or_default();
-        let port = gsettings_get("org.gnome.system.proxy.http", "port").unwrap_or_default();
-
-        let socks_host =
-            gsettings_get("org.gnome.system.proxy.socks", "host").unwrap_or_default();
-        let socks_port =
-            gsettings_get("org.gnome.system.proxy.socks", "port").unwrap_or_default();
-
-        let http = if !host.is_empty() && port != "0" {
-            format!("http://{}:{}", host, port)
-        } else {
-            String::new()
-        };
-
-        let socks = if !socks_host.is_empty() && socks_port != "0" {
-            format!("socks://{}:{}", socks_host, socks_port)
-        } else {
-            String::new()
-        };
-
-        if !http.is_empty() || !socks.is_empty() {
-            return ProxyInfo {
-                detected: true,
-                http,
-                socks,
-                source: "gsettings".to_string(),
-            };
-        }
-    }
-
-    ProxyInfo {
-        detected: false,
-        http: String::new(),
-        socks: String::new(),
-        source: "none".to_string(),
+        Err(AppError::new(
+            ErrorKind::CommandFailed,
+            format!(
+                "npm install failed (exit code {})",
+                output.status.code().unwrap_or(-1)
+            ),
+        )
+        .with_source(format!("{}\n{}", stdout, stderr).trim()))
     }
 }
 
+/// May fetch and evaluate a PAC script, so it stays off the main thread.
 #[tauri::command]
-pub fn detect_proxy() -> ProxyInfo {
-    detect_proxy_inner()
+#[tracing::instrument(err)]
+pub async fn detect_proxy() -> AppResult<ProxySettings> {
+    tauri::async_runtime::spawn_blocking(proxy::resolve)
+        .await
+        .map_err(AppError::task_join)
 }
 
 #[tauri::command]
+#[tracing::instrument(err, skip(api_key, telegram_token, discord_token, proxy_url))]
 pub fn save_initial_config(
     provider: String,
     api_key: String,
@@ -176,26 +141,45 @@ pub fn save_initial_config(
     telegram_token: Option<String>,
     discord_token: Option<String>,
     proxy_url: Option<String>,
-) -> Result<(), String> {
-    let home = dirs::home_dir().ok_or("Could not determine home directory")?;
-    let openclaw_dir = home.join(".openclaw");
-
-    // Create ~/.openclaw/ directory if it doesn't exist
-    fs::create_dir_all(&openclaw_dir)
-        .map_err(|e| format!("Failed to create ~/.openclaw: {}", e))?;
-
-    let config_path = openclaw_dir.join("openclaw.json");
+) -> AppResult<()> {
+    // Secrets are stored first; if the config that refers to them can't
+    // be written, don't leave them behind
+    let mut staged = secrets::Staged::default();
+    let result = write_initial_config(
+        &mut staged,
+        provider,
+        api_key,
+        model,
+        telegram_token,
+        discord_token,
+        proxy_url,
+    );
+    if result.is_err() {
+        staged.roll_back();
+    }
+    result
+}
 
-    // Build the config JSON
+fn write_initial_config(
+    staged: &mut secrets::Staged,
+    provider: String,
+    api_key: String,
+    model: String,
+    telegram_token: Option<String>,
+    discord_token: Option<String>,
+    proxy_url: Option<String>,
+) -> AppResult<()> {
+    // The key goes to secret storage; the config only names it
+    let api_key = staged.store_for(&format!("providers.{}.apiKey", provider), &api_key)?;
+
+    // Merged into any existing config, so rerunning the wizard keeps
+    // settings it doesn't ask about
+    let existing = match config::config_path().filter(|p| p.exists()) {
+        Some(path) => config::read_document(&path)?,
+        None => serde_json::Value::Null,
+    };
     let mut config = serde_json::json!({
-        "gateway": {
-            "port": 18789,
-            "bind": "loopback",
-            "auth": {
-                "mode": "token",
-                "token": generate_token()
-            }
-        },
+        "gateway": gateway_defaults(&existing["gateway"]),
         "providers": {
             provider.clone(): {
                 "apiKey": api_key
@@ -205,67 +189,134 @@ pub fn save_initial_config(
         "defaultModel": model
     });
 
-    // Add proxy config if provided
-    if let Some(ref proxy) = proxy_url {
-        if !proxy.is_empty() {
-            config["proxy"] = serde_json::json!({
-                "url": proxy
-            });
-        }
-    }
-
-    let config_str = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {}", e))?;
-
-    fs::write(&config_path, &config_str)
-        .map_err(|e| format!("Failed to write config: {}", e))?;
-
-    // Add channels via openclaw CLI if tokens provided
-    if let Some(ref token) = telegram_token {
-        if !token.is_empty() {
-            add_channel("telegram", token);
-        }
+    // Add proxy config if provided, with any password moved to secret
+    // storage
+    if let Some(proxy) = proxy_url.filter(|p| !p.is_empty()) {
+        let (url, credentials) = proxy::split_credentials(&proxy);
+        let (username, password) = match credentials {
+            Some((user, password)) if !password.is_empty() => (
+                Some(user),
+                Some(staged.store_for("proxy.password", &password)?),
+            ),
+            Some((user, _)) => (Some(user), None),
+            None => (None, None),
+        };
+        config["proxy"] = serde_json::json!({
+            "url": url,
+            "username": username,
+            "password": password
+        });
     }
 
-    if let Some(ref token) = discord_token {
-        if !token.is_empty() {
-            add_channel("discord", token);
+    // Add channels if tokens provided. They are written to the config by
+    // reference rather than through `openclaw channels add --token`, which
+    // would show the token to anyone running `ps`.
+    for (channel_type, token) in [("telegram", telegram_token), ("discord", discord_token)] {
+        if let Some(token) = token.filter(|t| !t.is_empty()) {
+            let token = staged.store_for(&format!("channels.{}.token", channel_type), &token)?;
+            config["channels"][channel_type] = serde_json::json!({
+                "enabled": true,
+                "token": token
+            });
         }
     }
 
+    config::update_config(&config)?;
     Ok(())
 }
 
-fn add_channel(channel_type: &str, token: &str) {
-    let result = Command::new("openclaw")
-        .args(["channels", "add", channel_type, "--token", token])
-        .output();
+/// The gateway settings a new config needs, leaving out those `existing`
+/// already has: a rerun must not move a port changed since, turn off LAN
+/// bind, or invalidate the token paired clients use.
+fn gateway_defaults(existing: &serde_json::Value) -> serde_json::Value {
+    let mut gateway = serde_json::json!({});
+    if existing.get("port").is_none() {
+        gateway["port"] = config::DEFAULT_PORT.into();
+    }
+    if existing.get("bind").is_none() {
+        gateway["bind"] = "loopback".into();
+    }
 
-    match result {
-        Ok(output) => {
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                eprintln!("Failed to add {} channel: {}", channel_type, stderr);
-            }
-        }
-        Err(e) => eprintln!("Failed to run openclaw channels add: {}", e),
+    let auth = &existing["auth"];
+    let mode = auth.get("mode").and_then(|m| m.as_str());
+    if mode.is_none() {
+        gateway["auth"]["mode"] = "token".into();
+    }
+    let has_token = auth
+        .get("token")
+        .and_then(|t| t.as_str())
+        .is_some_and(|t| !t.is_empty());
+    if mode.unwrap_or("token") == "token" && !has_token {
+        gateway["auth"]["token"] = generate_token().into();
     }
+    gateway
 }
 
-fn generate_token() -> String {
-    use std::collections::hash_map::RandomState;
-    use std::hash::{BuildHasher, Hasher};
+/// A 32-character [a-z0-9] token from the OS random number generator,
+/// about 165 bits of entropy.
+pub fn generate_token() -> String {
+    use aes_gcm::aead::rand_core::RngCore;
+    use aes_gcm::aead::OsRng;
+
+    const LEN: usize = 32;
+    const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    // Bytes from the last, partial run of 36 would favour the first characters
+    const LIMIT: u8 = (256 / CHARS.len() * CHARS.len()) as u8;
+
+    let mut token = String::with_capacity(LEN);
+    let mut buf = [0u8; 2 * LEN];
+    while token.len() < LEN {
+        OsRng.fill_bytes(&mut buf);
+        let missing = LEN - token.len();
+        token.extend(
+            buf.iter()
+                .filter(|b| **b < LIMIT)
+                .take(missing)
+                .map(|b| CHARS[*b as usize % CHARS.len()] as char),
+        );
+    }
+    token
+}
 
-    let mut result = String::with_capacity(32);
-    let chars = b"abcdefghijklmnopqrstuvwxyz0123456789";
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn gateway_defaults_fill_in_a_new_config() {
+        let gateway = gateway_defaults(&serde_json::Value::Null);
+        assert_eq!(gateway["port"], config::DEFAULT_PORT);
+        assert_eq!(gateway["bind"], "loopback");
+        assert_eq!(gateway["auth"]["mode"], "token");
+        assert_eq!(gateway["auth"]["token"].as_str().map(str::len), Some(32));
+    }
 
-    for _ in 0..32 {
-        let s = RandomState::new();
-        let mut hasher = s.build_hasher();
-        hasher.write_u8(0);
-        let idx = (hasher.finish() as usize) % chars.len();
-        result.push(chars[idx] as char);
+    #[test]
+    fn gateway_defaults_keep_existing_settings() {
+        let existing = json!({
+            "port": 18800,
+            "bind": "lan",
+            "auth": { "mode": "token", "token": "paired" }
+        });
+        assert_eq!(gateway_defaults(&existing), json!({}));
+
+        let no_auth = json!({ "port": 18800, "bind": "lan", "auth": { "mode": "none" } });
+        assert_eq!(gateway_defaults(&no_auth), json!({}));
+
+        let no_token = json!({ "port": 18800, "bind": "lan", "auth": { "mode": "token" } });
+        let gateway = gateway_defaults(&no_token);
+        assert!(gateway.get("port").is_none() && gateway.get("bind").is_none());
+        assert!(gateway["auth"]["token"].is_string());
     }
 
-    result
+    #[test]
+    fn tokens_are_random_alphanumerics() {
+        let token = generate_token();
+        assert_eq!(token.len(), 32);
+        assert!(token
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        assert_ne!(token, generate_token());
+    }
 }
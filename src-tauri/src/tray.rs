@@ -1,26 +1,47 @@
 use tauri::{
     image::Image,
-    menu::{MenuBuilder, MenuItem, MenuItemBuilder},
+    menu::{CheckMenuItemBuilder, MenuBuilder, MenuItem, MenuItemBuilder, Submenu, SubmenuBuilder},
     tray::TrayIconBuilder,
-    AppHandle, Emitter, Manager,
+    AppHandle, Emitter, Listener, Manager,
 };
 
-use crate::config;
-use crate::gateway;
+use tracing::warn;
+
+use crate::connection::{self, Connection};
+use crate::gateway::{self, GatewayState, LifecycleEvent};
+use crate::logs;
+use crate::profiles;
+use crate::settings;
+
+/// Menu id prefix for entries of the "Profiles" submenu.
+const PROFILE_ID_PREFIX: &str = "profile:";
+const DEFAULT_PROFILE_ID: &str = "profile-default";
+
+/// Handle to the "Profiles" submenu so it can be rebuilt when profiles change.
+struct ProfilesMenu(Submenu<tauri::Wry>);
 
 pub fn create_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
     let show = MenuItemBuilder::with_id("show", "Show Window").build(app)?;
     let hide = MenuItemBuilder::with_id("hide", "Hide Window").build(app)?;
+    let logs_item = MenuItemBuilder::with_id("logs", "Gateway Logs").build(app)?;
     let status = MenuItemBuilder::with_id("status", "Status: Checking...")
         .enabled(false)
         .build(app)?;
+    let lan = MenuItemBuilder::with_id("lan", "LAN Access: Off")
+        .enabled(false)
+        .build(app)?;
+    let profiles_menu = SubmenuBuilder::with_id(app, "profiles", "Profiles").build()?;
+    fill_profiles_menu(app, &profiles_menu)?;
     let quit = MenuItemBuilder::with_id("quit", "Quit").build(app)?;
 
     let menu = MenuBuilder::new(app)
         .item(&show)
         .item(&hide)
+        .item(&logs_item)
+        .item(&profiles_menu)
         .separator()
         .item(&status)
+        .item(&lan)
         .separator()
         .item(&quit)
         .build()?;
@@ -44,10 +65,20 @@ pub fn create_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
                     let _ = win.hide();
                 }
             }
+            "logs" => {
+                if let Err(e) = logs::show_window(app) {
+                    warn!(error = %e, "failed to open log window");
+                }
+            }
             "quit" => {
                 app.exit(0);
             }
-            _ => {}
+            DEFAULT_PROFILE_ID => switch_profile(app, None),
+            id => {
+                if let Some(name) = id.strip_prefix(PROFILE_ID_PREFIX) {
+                    switch_profile(app, Some(name.to_string()));
+                }
+            }
         })
         .on_tray_icon_event(|tray, event| {
             if let tauri::tray::TrayIconEvent::Click { .. } = event {
@@ -61,31 +92,115 @@ pub fn create_tray(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
         })
         .build(app)?;
 
+    app.manage(ProfilesMenu(profiles_menu));
+
+    // Reflect supervisor lifecycle changes immediately
+    let lifecycle_item = status.clone();
+    app.listen_any("gateway-lifecycle", move |event| {
+        let Ok(event) = serde_json::from_str::<LifecycleEvent>(event.payload()) else {
+            return;
+        };
+        // The tray reflects the gateway the main window uses
+        if event.instance == connection::main_instance() {
+            if let Err(e) = lifecycle_item.set_text(lifecycle_label(&event.state)) {
+                warn!(error = %e, "failed to update tray status");
+            }
+        }
+    });
+
     // Start health monitor with the status menu item so it can update the label
-    start_health_monitor(app.clone(), status);
+    start_health_monitor(app.clone(), status, lan);
 
     Ok(())
 }
 
-fn start_health_monitor(app: AppHandle, status_item: MenuItem<tauri::Wry>) {
+/// Rebuild the "Profiles" submenu so it shows the current list and checkmark.
+pub fn refresh_profiles(app: &AppHandle) {
+    let Some(menu) = app.try_state::<ProfilesMenu>() else {
+        return;
+    };
+    if let Err(e) = fill_profiles_menu(app, &menu.0) {
+        warn!(error = %e, "failed to update tray profiles");
+    }
+}
+
+fn fill_profiles_menu(app: &AppHandle, menu: &Submenu<tauri::Wry>) -> tauri::Result<()> {
+    for item in menu.items()? {
+        menu.remove(&item)?;
+    }
+
+    let s = settings::load_settings();
+    let default = CheckMenuItemBuilder::with_id(DEFAULT_PROFILE_ID, "Default")
+        .checked(s.active_profile().is_none())
+        .build(app)?;
+    menu.append(&default)?;
+
+    for p in profiles::list() {
+        let id = format!("{}{}", PROFILE_ID_PREFIX, p.name);
+        let item = CheckMenuItemBuilder::with_id(id, &p.name)
+            .checked(p.active)
+            .build(app)?;
+        menu.append(&item)?;
+    }
+    Ok(())
+}
+
+/// Switching restarts the gateway, so keep it off the event loop.
+fn switch_profile(app: &AppHandle, name: Option<String>) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = profiles::switch(&app, name.as_deref()) {
+            warn!(error = %e, "failed to switch profile");
+            // Undo the checkmark the click toggled
+            refresh_profiles(&app);
+        }
+    });
+}
+
+fn start_health_monitor(
+    app: AppHandle,
+    status_item: MenuItem<tauri::Wry>,
+    lan_item: MenuItem<tauri::Wry>,
+) {
     std::thread::spawn(move || {
         // Initial check after 5 seconds (faster first update)
         std::thread::sleep(std::time::Duration::from_secs(5));
 
         loop {
-            let status_text = match config::load_config() {
-                Ok(cfg) => {
-                    if gateway::check_health(&cfg.gateway.base_url()) {
-                        "Status: Online"
+            let conn = connection::current();
+
+            let lan_text = match &conn {
+                Ok(Connection::Local(gw)) => match gw.lan_url() {
+                    Some(url) => format!("LAN: {}", url),
+                    None => "LAN Access: Off".to_string(),
+                },
+                Ok(Connection::Remote { url, .. }) => {
+                    format!("Remote: {}", url.host_str().unwrap_or_default())
+                }
+                Err(_) => "LAN Access: Off".to_string(),
+            };
+            if let Err(e) = lan_item.set_text(&lan_text) {
+                warn!(error = %e, "failed to update tray LAN item");
+            }
+
+            let status_text = match conn {
+                Ok(conn) => {
+                    if gateway::check_health(&conn.base_url()) {
+                        "Status: Online".to_string()
                     } else {
-                        "Status: Offline"
+                        match gateway::current_state() {
+                            state @ (GatewayState::Backoff { .. }
+                            | GatewayState::Failed { .. }
+                            | GatewayState::Conflict(_)) => lifecycle_label(&state),
+                            _ => "Status: Offline".to_string(),
+                        }
                     }
                 }
-                Err(_) => "Status: No Config",
+                Err(_) => "Status: No Config".to_string(),
             };
 
-            if let Err(e) = status_item.set_text(status_text) {
-                eprintln!("Failed to update tray status: {}", e);
+            if let Err(e) = status_item.set_text(&status_text) {
+                warn!(error = %e, "failed to update tray status");
             }
 
             // Also emit event for frontend
@@ -95,3 +210,20 @@ fn start_health_monitor(app: AppHandle, status_item: MenuItem<tauri::Wry>) {
         }
     });
 }
+
+fn lifecycle_label(state: &GatewayState) -> String {
+    match state {
+        GatewayState::Stopped => "Status: Stopped".to_string(),
+        GatewayState::Starting => "Status: Starting...".to_string(),
+        GatewayState::Running { .. } => "Status: Online".to_string(),
+        GatewayState::Attached { .. } => "Status: Online (attached)".to_string(),
+        GatewayState::Remote { .. } => "Status: Connecting (remote)...".to_string(),
+        GatewayState::Exited { code: Some(code) } => format!("Status: Exited ({})", code),
+        GatewayState::Exited { code: None } => "Status: Exited (signal)".to_string(),
+        GatewayState::Backoff { delay_ms, .. } => {
+            format!("Status: Restarting in {}s", delay_ms.div_ceil(1000))
+        }
+        GatewayState::Failed { .. } => "Status: Failed (see log)".to_string(),
+        GatewayState::Conflict(c) => format!("Status: Port {} in use", c.port),
+    }
+}
@@ -0,0 +1,169 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter};
+use tracing::{info, warn};
+
+use crate::config::{self, ConfigChange, OpenClawConfig};
+use crate::error::AppError;
+use crate::gateway;
+use crate::instance::{self, Instance};
+use crate::settings;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Editors and the CLI may write a file in several steps; only act once it
+/// has been stable this long.
+const DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// Payload of `config-changed`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChangedEvent {
+    pub instance: String,
+    pub path: PathBuf,
+    /// False if the new file doesn't parse or validate. The gateway keeps
+    /// running with the last good config and `error` says what's wrong.
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<AppError>,
+    pub changes: Vec<ConfigChange>,
+    /// Edited outside the app (by hand or by the `openclaw` CLI).
+    pub external: bool,
+    /// Some change needs a gateway restart to take effect.
+    pub gateway_changed: bool,
+    /// The watcher is restarting the gateway on its own.
+    pub restarting: bool,
+}
+
+/// What we know about one watched config file.
+struct Watched {
+    /// Modification time and size at the last poll.
+    stamp: Option<(SystemTime, u64)>,
+    /// When `stamp` last changed, while waiting for the file to settle.
+    changed_at: Option<Instant>,
+    /// Last version that parsed and validated, the base for diffs.
+    last_good: Option<Value>,
+}
+
+impl Watched {
+    fn new(inst: &Instance) -> Self {
+        Self {
+            stamp: stamp(inst),
+            changed_at: None,
+            last_good: read_valid(inst).ok(),
+        }
+    }
+}
+
+/// Poll every instance's openclaw.json and react to changes: emit
+/// `config-changed` with a diff and restart gateways whose startup settings
+/// changed.
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || {
+        let mut watched: HashMap<String, Watched> = HashMap::new();
+
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+
+            for inst in instance::all() {
+                let w = watched
+                    .entry(inst.id.clone())
+                    .or_insert_with(|| Watched::new(&inst));
+                if let Some(event) = poll(&inst, w) {
+                    if let Err(e) = app.emit("config-changed", &event) {
+                        warn!(error = %e, "failed to emit config change");
+                    }
+                }
+            }
+        }
+    });
+}
+
+fn stamp(inst: &Instance) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(&inst.config_path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+fn poll(inst: &Instance, w: &mut Watched) -> Option<ConfigChangedEvent> {
+    let current = stamp(inst);
+    if current != w.stamp {
+        w.stamp = current;
+        w.changed_at = Some(Instant::now());
+        return None;
+    }
+    if !matches!(w.changed_at, Some(t) if t.elapsed() >= DEBOUNCE) {
+        return None;
+    }
+    w.changed_at = None;
+    // A deleted file is usually about to be replaced; keep the last good one
+    current?;
+
+    let doc = match read_valid(inst) {
+        Ok(doc) => doc,
+        Err(error) => {
+            warn!(instance = %inst.id, error = %error, "changed config is invalid, ignoring");
+            return Some(ConfigChangedEvent {
+                instance: inst.id.clone(),
+                path: inst.config_path.clone(),
+                valid: false,
+                error: Some(error),
+                changes: Vec::new(),
+                external: true,
+                gateway_changed: false,
+                restarting: false,
+            });
+        }
+    };
+
+    let changes = w
+        .last_good
+        .as_ref()
+        .map(|old| config::diff(old, &doc))
+        .unwrap_or_default();
+    let external = !config::is_own_write(&inst.config_path, &doc);
+    w.last_good = Some(doc);
+    if changes.is_empty() {
+        return None;
+    }
+
+    let gateway_changed = changes.iter().any(ConfigChange::affects_gateway);
+    // Our own writes are followed by a restart where one is needed
+    let restarting = external
+        && gateway_changed
+        && settings::load_settings().gateway.restart_on_config_change;
+    info!(
+        instance = %inst.id,
+        changes = changes.len(),
+        external,
+        gateway_changed,
+        restarting,
+        "config changed"
+    );
+
+    if restarting {
+        let id = inst.id.clone();
+        std::thread::spawn(move || gateway::restart_if_managed(&id));
+    }
+
+    Some(ConfigChangedEvent {
+        instance: inst.id.clone(),
+        path: inst.config_path.clone(),
+        valid: true,
+        error: None,
+        changes,
+        external,
+        gateway_changed,
+        restarting,
+    })
+}
+
+/// Read the config as a document, checking it also parses as `OpenClawConfig`.
+fn read_valid(inst: &Instance) -> Result<Value, AppError> {
+    let path = &inst.config_path;
+    let doc = config::read_document(path)?;
+    serde_json::from_value::<OpenClawConfig>(doc.clone())
+        .map_err(|e| AppError::config_invalid(path, &e))?;
+    Ok(doc)
+}
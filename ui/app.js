@@ -4,6 +4,7 @@ const { listen } = window.__TAURI__.event;
 const RETRY_INTERVAL_MS = 5000;
 let retryTimer = null;
 let countdownTimer = null;
+let suggestedPort = null;
 
 function showScreen(id) {
   document.querySelectorAll(".screen").forEach((el) => el.classList.remove("active"));
@@ -16,6 +17,34 @@ function setLoadingStatus(msg) {
   if (el) el.textContent = msg;
 }
 
+// Commands reject with { kind, message, path?, line?, column?, source? }
+function errorMessage(err) {
+  if (!err || typeof err !== "object") return String(err);
+  return err.source ? `${err.message}: ${err.source}` : err.message;
+}
+
+function showCommandError(err) {
+  switch (err && err.kind) {
+    case "config_missing":
+      // No config yet: send the user through the setup wizard
+      window.location.replace("setup.html");
+      break;
+    case "config_parse":
+      showError(
+        "Config Syntax Error",
+        `${err.path}, line ${err.line}, column ${err.column}: ${err.source}`
+      );
+      document.getElementById("doctor-btn").style.display = "inline-block";
+      break;
+    case "config_invalid":
+      showError("Invalid Config", errorMessage(err));
+      document.getElementById("doctor-btn").style.display = "inline-block";
+      break;
+    default:
+      showError("Configuration Error", errorMessage(err));
+  }
+}
+
 function showError(title, message) {
   document.getElementById("error-title").textContent = title;
   document.getElementById("error-message").textContent = message;
@@ -25,6 +54,118 @@ function showError(title, message) {
   // Show proxy hint for connection errors
   const hint = document.getElementById("proxy-hint");
   if (hint) hint.style.display = "block";
+  document.getElementById("proxy-test-btn").style.display = "inline-block";
+  document.getElementById("proxy-test-result").style.display = "none";
+
+  document.getElementById("port-fix-btn").style.display = "none";
+  document.getElementById("doctor-btn").style.display = "none";
+}
+
+function showPortConflict(conflict) {
+  showError("Port In Use", `${conflict.message}.`);
+  suggestedPort = conflict.suggested_port;
+  if (suggestedPort) {
+    const btn = document.getElementById("port-fix-btn");
+    btn.textContent = `Use Port ${suggestedPort}`;
+    btn.style.display = "inline-block";
+  }
+}
+
+async function usePort() {
+  clearTimers();
+  showScreen("loading");
+  setLoadingStatus(`Moving gateway to port ${suggestedPort}...`);
+  try {
+    await invoke("resolve_port_conflict", { port: suggestedPort });
+    await connectToGateway();
+  } catch (err) {
+    showCommandError(err);
+  }
+}
+
+function renderDiagnostic(diag) {
+  const item = document.createElement("label");
+  item.className = `check-item ${diag.severity === "error" ? "fail" : "warning"}`;
+
+  const box = document.createElement("input");
+  box.type = "checkbox";
+  box.value = diag.id;
+  box.disabled = !diag.fix;
+  box.checked = !!diag.fix && diag.severity === "error";
+  item.appendChild(box);
+
+  const text = document.createElement("span");
+  text.className = "check-label";
+  const where = diag.line ? ` (line ${diag.line}, column ${diag.column})` : "";
+  text.textContent = `${diag.path || "openclaw.json"}${where}: ${diag.message}`;
+  item.appendChild(text);
+
+  if (diag.fix) {
+    const fix = document.createElement("span");
+    fix.className = "check-detail";
+    fix.textContent = `Fix: ${diag.fix.description}`;
+    item.appendChild(fix);
+  }
+  return item;
+}
+
+function showDoctorReport(report) {
+  document.getElementById("doctor-path").textContent = report.path;
+  const list = document.getElementById("doctor-list");
+  if (report.diagnostics.length === 0) {
+    list.textContent = "No problems found.";
+  } else {
+    list.replaceChildren(...report.diagnostics.map(renderDiagnostic));
+  }
+  document.getElementById("doctor-apply-btn").style.display =
+    report.diagnostics.some((d) => d.fix) ? "inline-block" : "none";
+  showScreen("doctor");
+}
+
+// Check the system proxy by tunnelling through it to Telegram
+async function testProxy() {
+  const btn = document.getElementById("proxy-test-btn");
+  const result = document.getElementById("proxy-test-result");
+  btn.disabled = true;
+  result.style.display = "block";
+  result.textContent = "Testing proxy...";
+  try {
+    const r = await invoke("test_proxy", {});
+    if (r.status === "ok") {
+      const total = (r.connect_ms || 0) + (r.tunnel_ms || 0) + (r.tls_ms || 0);
+      result.textContent = `Proxy ${r.proxy} reaches ${r.target} (${total} ms).`;
+    } else {
+      result.textContent = `Proxy ${r.proxy}: ${r.message || r.status}`;
+    }
+  } catch (err) {
+    result.textContent = errorMessage(err);
+  } finally {
+    btn.disabled = false;
+  }
+}
+
+async function runDoctor() {
+  clearTimers();
+  try {
+    showDoctorReport(await invoke("check_config"));
+  } catch (err) {
+    showCommandError(err);
+  }
+}
+
+async function applyFixes() {
+  const ids = [...document.querySelectorAll("#doctor-list input:checked")].map((el) => el.value);
+  if (ids.length === 0) return;
+  try {
+    const report = await invoke("apply_config_fixes", { ids });
+    if (report.ok) {
+      await retryConnection();
+    } else {
+      showDoctorReport(report);
+    }
+  } catch (err) {
+    showCommandError(err);
+  }
 }
 
 function startRetryCountdown() {
@@ -78,13 +219,18 @@ async function connectToGateway() {
         window.location.replace(info.full_url);
       }, 300);
     } else {
+      const conflict = await invoke("check_port_conflict");
+      if (conflict) {
+        showPortConflict(conflict);
+        return;
+      }
       showError(
         "Gateway Offline",
-        `Cannot reach OpenClaw gateway at 127.0.0.1:${info.port}. Make sure the service is running.`
+        `Cannot reach OpenClaw gateway at ${info.url}. Make sure the service is running.`
       );
     }
   } catch (err) {
-    showError("Configuration Error", String(err));
+    showCommandError(err);
   }
 }
 
@@ -100,5 +246,84 @@ listen("gateway-status", async (event) => {
   }
 });
 
+// Listen for gateway supervisor lifecycle events
+listen("gateway-lifecycle", async (event) => {
+  const lifecycle = event.payload;
+  // Extra local instances don't affect the main window
+  if (lifecycle.instance !== "default") return;
+  const onError = document.getElementById("error").classList.contains("active");
+
+  switch (lifecycle.state) {
+    case "starting":
+      setLoadingStatus("Starting gateway...");
+      break;
+    case "backoff":
+      setLoadingStatus(`Gateway exited, restarting in ${Math.ceil(lifecycle.delay_ms / 1000)}s...`);
+      break;
+    case "failed":
+      showError("Gateway Failed", lifecycle.reason);
+      break;
+    case "conflict":
+      showPortConflict(lifecycle);
+      break;
+    case "running":
+    case "attached":
+    case "remote":
+      if (onError) {
+        clearTimers();
+        showScreen("loading");
+        setLoadingStatus("Gateway is back online! Reconnecting...");
+        await connectToGateway();
+      }
+      break;
+  }
+});
+
+function showConfigNotice(text, offerRestart) {
+  document.getElementById("config-notice-text").textContent = text;
+  document.getElementById("config-restart-btn").style.display = offerRestart ? "inline-block" : "none";
+  document.getElementById("config-notice").style.display = "flex";
+}
+
+function hideConfigNotice() {
+  document.getElementById("config-notice").style.display = "none";
+}
+
+async function restartGateway() {
+  hideConfigNotice();
+  clearTimers();
+  showScreen("loading");
+  setLoadingStatus("Restarting gateway...");
+  try {
+    await invoke("start_gateway");
+    await connectToGateway();
+  } catch (err) {
+    showCommandError(err);
+  }
+}
+
+// openclaw.json was edited on disk (by hand or by the openclaw CLI)
+listen("config-changed", async (event) => {
+  const change = event.payload;
+  if (change.instance !== "default") return;
+
+  if (!change.valid) {
+    const err = change.error;
+    const where = err.line ? ` (line ${err.line}, column ${err.column})` : "";
+    showConfigNotice(`openclaw.json has errors${where}, keeping the previous config: ${errorMessage(err)}`, false);
+    return;
+  }
+  if (!change.external) return;
+
+  const paths = change.changes.map((c) => c.path).join(", ");
+  if (change.restarting) {
+    showConfigNotice(`openclaw.json changed (${paths}), restarting gateway...`, false);
+  } else if (change.gateway_changed) {
+    showConfigNotice(`openclaw.json changed (${paths}). Restart the gateway to apply.`, true);
+  } else {
+    showConfigNotice(`openclaw.json changed (${paths}).`, false);
+  }
+});
+
 // Start connection on load
 connectToGateway();
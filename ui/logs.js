@@ -0,0 +1,121 @@
+const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+
+const MAX_LINES = 5000;
+const LEVEL_ORDER = ["trace", "debug", "info", "warn", "error", "fatal"];
+
+const linesEl = document.getElementById("log-lines");
+const instanceEl = document.getElementById("log-instance");
+const searchEl = document.getElementById("log-search");
+const levelEl = document.getElementById("log-level");
+const followEl = document.getElementById("log-follow");
+const appLevelEl = document.getElementById("app-log-level");
+
+let searchTimer = null;
+
+// Commands reject with { kind, message, path?, line?, column?, source? }
+function errorMessage(err) {
+  if (!err || typeof err !== "object") return String(err);
+  return err.source ? `${err.message}: ${err.source}` : err.message;
+}
+
+function matchesFilter(line) {
+  const level = levelEl.value;
+  if (level && line.level && LEVEL_ORDER.indexOf(line.level) < LEVEL_ORDER.indexOf(level)) {
+    return false;
+  }
+  const query = searchEl.value.trim().toLowerCase();
+  return !query || line.message.toLowerCase().includes(query);
+}
+
+function renderLine(line) {
+  const row = document.createElement("div");
+  row.className = `log-line level-${line.level || "none"}`;
+
+  if (line.timestamp) {
+    const ts = document.createElement("span");
+    ts.className = "log-ts";
+    ts.textContent = line.timestamp;
+    row.appendChild(ts);
+  }
+  if (line.level) {
+    const lvl = document.createElement("span");
+    lvl.className = "log-level";
+    lvl.textContent = line.level.toUpperCase();
+    row.appendChild(lvl);
+  }
+  const msg = document.createElement("span");
+  msg.textContent = line.message;
+  row.appendChild(msg);
+  return row;
+}
+
+function appendLines(lines) {
+  const fragment = document.createDocumentFragment();
+  lines.filter(matchesFilter).forEach((line) => fragment.appendChild(renderLine(line)));
+  linesEl.appendChild(fragment);
+
+  while (linesEl.childElementCount > MAX_LINES) {
+    linesEl.removeChild(linesEl.firstChild);
+  }
+  if (followEl.checked) {
+    linesEl.scrollTop = linesEl.scrollHeight;
+  }
+}
+
+async function reload() {
+  const level = levelEl.value || null;
+  const query = searchEl.value.trim();
+  try {
+    const lines = query
+      ? await invoke("search_gateway_log", { query, level, instance: instanceEl.value })
+      : await invoke("tail_gateway_log", { level, instance: instanceEl.value });
+    linesEl.replaceChildren();
+    appendLines(lines);
+  } catch (err) {
+    linesEl.textContent = errorMessage(err);
+  }
+}
+
+searchEl.addEventListener("input", () => {
+  clearTimeout(searchTimer);
+  searchTimer = setTimeout(reload, 300);
+});
+levelEl.addEventListener("change", reload);
+instanceEl.addEventListener("change", reload);
+
+appLevelEl.addEventListener("change", async () => {
+  try {
+    await invoke("set_log_level", { level: appLevelEl.value });
+  } catch (err) {
+    console.error(errorMessage(err));
+  }
+});
+
+invoke("get_log_level").then((level) => {
+  appLevelEl.value = level;
+});
+
+async function loadInstances() {
+  try {
+    const instances = await invoke("list_gateway_instances");
+    if (instances.length < 2) return;
+    instanceEl.replaceChildren(
+      ...instances.map((inst) => {
+        const opt = document.createElement("option");
+        opt.value = inst.id;
+        opt.textContent = inst.port ? `${inst.id} (:${inst.port})` : inst.id;
+        return opt;
+      })
+    );
+  } catch (err) {
+    console.error(errorMessage(err));
+  }
+}
+
+listen("gateway-log", (event) => {
+  if (event.payload.instance === instanceEl.value) appendLines(event.payload.lines);
+});
+
+loadInstances();
+reload();
@@ -1,6 +1,12 @@
 const { invoke } = window.__TAURI__.core;
 const { listen } = window.__TAURI__.event;
 
+// Commands reject with { kind, message, path?, line?, column?, source? }
+function errorMessage(err) {
+  if (!err || typeof err !== "object") return String(err);
+  return err.source ? `${err.message}: ${err.source}` : err.message;
+}
+
 let currentStep = 1;
 let prereqStatus = null;
 let proxyInfo = null;
@@ -134,7 +140,7 @@ async function checkPrereqs() {
     document.querySelector("#step-1 .step-desc").textContent = "环境检测完成";
   } catch (err) {
     document.querySelector("#step-1 .step-desc").textContent =
-      "检测失败: " + String(err);
+      "检测失败: " + errorMessage(err);
   }
 }
 
@@ -167,7 +173,7 @@ async function installOpenclaw() {
     // Re-check prerequisites
     await recheckPrereqs();
   } catch (err) {
-    result.textContent = "安装失败: " + String(err);
+    result.textContent = "安装失败: " + errorMessage(err);
     result.classList.add("fail");
     progress.style.display = "none";
     btn.style.display = "inline-block";
@@ -195,6 +201,15 @@ function validateAndNext(nextStep) {
   goToStep(nextStep);
 }
 
+const PROXY_SOURCES = {
+  env: "环境变量",
+  gnome: "GNOME 设置",
+  kde: "KDE 设置",
+  portal: "桌面门户",
+  etc_environment: "/etc/environment",
+  config: "配置文件",
+};
+
 async function detectProxy() {
   const statusEl = document.getElementById("proxy-status");
   const detectedEl = document.getElementById("proxy-detected");
@@ -209,32 +224,89 @@ async function detectProxy() {
 
     statusEl.style.display = "none";
 
-    if (proxyInfo.detected) {
+    if (proxyInfo.source !== "none") {
       detectedEl.style.display = "block";
-      let detail = proxyInfo.http;
-      if (proxyInfo.socks) {
-        detail += " / SOCKS: " + proxyInfo.socks;
+      const parts = [];
+      if (proxyInfo.http) parts.push(proxyInfo.http);
+      if (proxyInfo.https && proxyInfo.https !== proxyInfo.http) {
+        parts.push("HTTPS: " + proxyInfo.https);
+      }
+      if (proxyInfo.socks) parts.push("SOCKS: " + proxyInfo.socks);
+      if (proxyInfo.pac_url) {
+        if (parts.length === 0) parts.push("直连");
+        parts.push("PAC: " + proxyInfo.pac_url);
       }
-      detail += " (来源: " + proxyInfo.source + ")";
+      const detail =
+        parts.join(" / ") + " (来源: " + (PROXY_SOURCES[proxyInfo.source] || proxyInfo.source) + ")";
       document.getElementById("proxy-detail-text").textContent = detail;
+      document.getElementById("proxy-test").style.display = getProxyUrl() ? "block" : "none";
     } else {
       notDetectedEl.style.display = "block";
+      document.getElementById("proxy-test").style.display = "block";
     }
   } catch (err) {
     statusEl.style.display = "none";
     notDetectedEl.style.display = "block";
+    document.getElementById("proxy-test").style.display = "block";
   }
 }
 
+const PROXY_TEST_STATUS = {
+  ok: "代理可用",
+  unreachable: "无法连接到代理",
+  timeout: "连接超时",
+  auth_required: "代理需要用户名和密码",
+  auth_failed: "代理用户名或密码错误",
+  rejected: "代理拒绝连接目标",
+  tls_error: "TLS 握手失败",
+  protocol_error: "代理协议不匹配",
+};
+
+async function testProxy() {
+  const btn = document.getElementById("proxy-test-btn");
+  const result = document.getElementById("proxy-test-result");
+  // The system proxy is tested by the backend, which knows its credentials
+  const url = getManualProxyUrl() || null;
+  if (!url && !getProxyUrl()) {
+    result.className = "install-result fail";
+    result.textContent = "请先输入代理地址";
+    return;
+  }
+
+  btn.disabled = true;
+  result.className = "install-result";
+  result.textContent = "正在测试...";
+  try {
+    const r = await invoke("test_proxy", { url });
+    const label = PROXY_TEST_STATUS[r.status] || r.status;
+    if (r.status === "ok") {
+      const total = (r.connect_ms || 0) + (r.tunnel_ms || 0) + (r.tls_ms || 0);
+      result.className = "install-result success";
+      result.textContent = `${label}：${r.target} 延迟 ${total} ms`;
+    } else {
+      result.className = "install-result fail";
+      result.textContent = r.message ? `${label}：${r.message}` : label;
+    }
+  } catch (err) {
+    result.className = "install-result fail";
+    result.textContent = errorMessage(err);
+  } finally {
+    btn.disabled = false;
+  }
+}
+
+// The detected proxy, with any password redacted, or the manual one
 function getProxyUrl() {
-  if (proxyInfo && proxyInfo.detected) {
-    return proxyInfo.http;
+  if (proxyInfo && proxyInfo.source !== "none") {
+    return proxyInfo.http || proxyInfo.https || proxyInfo.socks || "";
   }
+  return getManualProxyUrl();
+}
+
+function getManualProxyUrl() {
+  if (proxyInfo && proxyInfo.source !== "none") return "";
   const manual = document.getElementById("manual-proxy");
-  if (manual) {
-    return manual.value.trim();
-  }
-  return "";
+  return manual ? manual.value.trim() : "";
 }
 
 function maskKey(key) {
@@ -273,7 +345,9 @@ async function saveAndLaunch() {
   const model = document.getElementById("model-input").value.trim();
   const telegram = document.getElementById("telegram-token").value.trim() || null;
   const discord = document.getElementById("discord-token").value.trim() || null;
-  const proxy = getProxyUrl() || null;
+  // The system proxy is detected again at every launch; only a manual
+  // one needs saving
+  const proxy = getManualProxyUrl() || null;
 
   try {
     await invoke("save_initial_config", {
@@ -297,7 +371,7 @@ async function saveAndLaunch() {
       window.location.replace("index.html");
     }, 500);
   } catch (err) {
-    errorEl.textContent = "保存失败: " + String(err);
+    errorEl.textContent = "保存失败: " + errorMessage(err);
     errorEl.style.display = "block";
     savingEl.style.display = "none";
     launchBtn.disabled = false;